@@ -1,3 +1,7 @@
+pub static DEFAULT_CONFIG_PATH: &'static str = "./miner.conf";
+
+pub static MINER_VERSION: &'static str = concat!("GPU Miner ", env!("CARGO_PKG_VERSION"));
+
 pub const INITIAL_SEED_SIZE: usize = 256;
 
 pub static ARGON2_BLOCK_SIZE: u64 = 1024;
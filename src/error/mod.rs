@@ -1,11 +1,13 @@
 use base64::DecodeError as Decode64;
 use beserial::SerializingError as BESerial;
 use futures::sync::mpsc::SendError;
+use native_tls::Error as NativeTLS;
 use nimiq_lib::error::ClientError;
 use serde::Deserialize;
 use serde::Deserializer;
 use serde_json::Error as Serde;
 use std::io::Error as IOError;
+use std::path::PathBuf;
 use tungstenite::error::Error as Tungstenite;
 
 use crate::ffi::cl_int;
@@ -18,6 +20,8 @@ pub enum Error {
   Connect(Tungstenite),
   Send(Tungstenite),
   Read(Tungstenite),
+  TLS(NativeTLS),
+  Disconnected,
   Custom(String),
   Serde(Serde),
   SendMessage(SendError<PoolMessage>),
@@ -25,6 +29,9 @@ pub enum Error {
   BESerial(BESerial),
   NimiqClient(ClientError),
   IO(IOError),
+  Config(String, String),
+  Invalid(Vec<(String, String)>),
+  ConfigFile(PathBuf, IOError),
 }
 
 impl From<&str> for Error {
@@ -39,6 +46,12 @@ impl From<String> for Error {
   }
 }
 
+impl From<NativeTLS> for Error {
+  fn from(other: NativeTLS) -> Self {
+    Error::TLS(other)
+  }
+}
+
 impl From<Serde> for Error {
   fn from(other: Serde) -> Self {
     Error::Serde(other)
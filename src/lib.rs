@@ -10,4 +10,5 @@ pub mod hash;
 pub mod miner;
 pub mod opencl;
 pub mod pool;
+pub mod solo;
 pub mod utils;
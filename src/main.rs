@@ -14,41 +14,672 @@ use nimiq_network::network_config::Seed;
 use nimiq_network::Network;
 use nimiq_network_primitives::address::PeerAddress;
 use nimiq_network_primitives::address::PeerUri;
-use nimiq_network_primitives::networks::get_network_info;
 use nimiq_network_primitives::networks::NetworkInfo;
 use nimiq_network_primitives::protocol::Protocol;
-use nimiq_primitives::networks::NetworkId;
 
+use clap::App;
+use clap::AppSettings;
+use clap::Arg;
+use clap::ArgMatches;
+use clap::SubCommand;
+use futures::sync::mpsc::unbounded;
 use futures::Future;
 use futures::IntoFuture;
 use futures::Stream;
+use serde_json::to_string_pretty;
+use signal_hook::iterator::Signals;
+use signal_hook::SIGHUP;
+use signal_hook::SIGUSR1;
+use std::fs::create_dir_all;
+use std::io::ErrorKind;
+use std::process::exit;
 use std::str::FromStr;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::sync::RwLock;
+use std::thread::sleep;
+use std::thread::spawn;
+use std::time::Duration;
+use std::time::Instant;
 use tokio::run;
 
+use plutonium::config::DEFAULT_CONFIG_PATH;
+use plutonium::config::MINER_VERSION;
+use plutonium::config::ONE_MB;
 use plutonium::error::Error;
+use plutonium::miner::get_devices;
+use plutonium::miner::Device;
+use plutonium::miner::Miner;
+use plutonium::miner::MinerConfig;
+use plutonium::pool::load_device_id;
+use plutonium::pool::DeviceData;
 use plutonium::pool::PoolClient;
 use plutonium::pool::PoolConfig;
+use plutonium::pool::PoolEndpoint;
+use plutonium::pool::PoolEvent;
+use plutonium::pool::PoolMessage;
 use plutonium::pool::PoolMiner;
-
-const MINER_VERSION: &'static str = concat!("GPU Miner ", env!("CARGO_PKG_VERSION"));
+use plutonium::pool::PoolMode;
+use plutonium::pool::PoolProxy;
+use plutonium::pool::ShareReport;
+use plutonium::pool::ShareTotals;
+use plutonium::pool::CONFIG_KEYS;
+use plutonium::solo::SoloMiner;
 
 const TAG: &'static str = "SushiPoolMiner";
 
 static ESTABLISHED: AtomicBool = AtomicBool::new(false);
 
-fn main() -> Result<(), Error> {
-  let config: PoolConfig = PoolConfig::default();
+// Share target used by `verify`, roughly one share per 512 hashes
+const VERIFY_COMPACT: u32 = 0x1f7f_ffff;
+const VERIFY_RUNS: usize = 64;
+
+// How often higher priority pools are probed after a failover
+const PROBE_INTERVAL: Duration = Duration::from_secs(300);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// How often the mining time of splits is accounted
+const SPLIT_INTERVAL: Duration = Duration::from_secs(10);
+
+// How often the share rate is compared to the measured hashrate
+const DIFFICULTY_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+// How often the mempool is checked for transactions worth a new block
+const MEMPOOL_INTERVAL: Duration = Duration::from_secs(10);
+
+// How long `payout` waits for the pool to confirm the request
+const PAYOUT_TIMEOUT: Duration = Duration::from_secs(60);
+
+fn main() {
+  if let Err(error) = run() {
+    report(&error);
+    exit(1);
+  }
+}
+
+fn run() -> Result<(), Error> {
+  let matches: ArgMatches = app().get_matches();
+
+  if leaf(&matches).is_present("print-effective-config") {
+    return print_config(load_config(leaf(&matches))?);
+  }
+
+  match matches.subcommand() {
+    ("mine", Some(args)) => mine(args.clone()),
+    ("devices", Some(_)) => devices(),
+    ("benchmark", Some(args)) => benchmark(
+      load_config(args)?,
+      parse_arg("seconds", args.value_of("seconds").unwrap())?,
+    ),
+    ("verify", Some(args)) => verify(load_config(args)?),
+    ("payout", Some(args)) => payout(load_config(args)?),
+    ("config", Some(args)) => match args.subcommand() {
+      ("check", Some(args)) => check(load_valid_config(args)?),
+      _ => unreachable!(),
+    },
+    _ => mine(matches.clone()),
+  }
+}
+
+fn report(error: &Error) {
+  match error {
+    Error::Invalid(errors) => {
+      eprintln!("[{}] Invalid config", TAG);
+
+      for (key, message) in errors {
+        eprintln!("[{}] - {}: {}", TAG, key, message);
+      }
+    }
+    Error::Config(key, message) => {
+      eprintln!("[{}] Invalid config", TAG);
+      eprintln!("[{}] - {}: {}", TAG, key, message);
+    }
+    error => {
+      eprintln!("[{}] Error: {:?}", TAG, error);
+    }
+  }
+}
+
+/// Loads the config and checks it against the available GPU devices.
+///
+/// Problems of every source and of the merged config are reported at once.
+fn load_valid_config(args: &ArgMatches) -> Result<PoolConfig, Error> {
+  let (config, mut errors) = merge_config(args)?;
+  let devices: Vec<Device> = unsafe { get_devices()? };
+
+  collect(&mut errors, config.validate(&devices))?;
+
+  if errors.is_empty() {
+    Ok(config)
+  } else {
+    Err(Error::Invalid(errors))
+  }
+}
+
+fn app() -> App<'static, 'static> {
+  App::new("plutonium")
+    .version(env!("CARGO_PKG_VERSION"))
+    .about("Nimiq GPU pool miner")
+    .setting(AppSettings::VersionlessSubcommands)
+    .arg(
+      Arg::with_name("config")
+        .short("c")
+        .long("config")
+        .value_name("FILE")
+        .help("Config file to load")
+        .default_value(DEFAULT_CONFIG_PATH)
+        .global(true),
+    )
+    .arg(
+      Arg::with_name("address")
+        .long("address")
+        .value_name("ADDRESS")
+        .help("Wallet address, overrides `address`")
+        .global(true),
+    )
+    .arg(
+      Arg::with_name("payout_key")
+        .long("payout-key")
+        .value_name("HEX")
+        .help("Private key of the wallet, signs payout requests, overrides `payout_key`")
+        .global(true),
+    )
+    .arg(
+      Arg::with_name("host")
+        .long("host")
+        .value_name("HOST")
+        .help("Pool server, overrides `host`")
+        .global(true),
+    )
+    .arg(
+      Arg::with_name("port")
+        .long("port")
+        .value_name("PORT")
+        .help("Pool port, overrides `port`")
+        .global(true),
+    )
+    .arg(
+      Arg::with_name("scheme")
+        .long("scheme")
+        .value_name("SCHEME")
+        .help("Pool scheme, `ws` or `wss`, overrides `scheme`")
+        .possible_values(&["ws", "wss"])
+        .global(true),
+    )
+    .arg(
+      Arg::with_name("tls_ca")
+        .long("tls-ca")
+        .value_name("FILE")
+        .help("PEM file of additional CA certificates, overrides `tls_ca`")
+        .global(true),
+    )
+    .arg(
+      Arg::with_name("tls_pins")
+        .long("tls-pins")
+        .value_name("SHA256")
+        .help("Comma-separated SHA-256 fingerprints of pool certificates, overrides `tls_pins`")
+        .use_delimiter(true)
+        .global(true),
+    )
+    .arg(
+      Arg::with_name("tls_insecure")
+        .long("tls-insecure")
+        .help("Accepts any pool certificate, for testing only")
+        .global(true),
+    )
+    .arg(
+      Arg::with_name("proxy")
+        .long("proxy")
+        .value_name("URL")
+        .help("Proxy of the pool connections, `socks5://` or `http://`, overrides `proxy`")
+        .global(true),
+    )
+    .arg(
+      Arg::with_name("network")
+        .long("network")
+        .value_name("NETWORK")
+        .help("Nimiq network, `main`, `test` or `dev`, overrides `network`")
+        .possible_values(&["main", "test", "dev"])
+        .global(true),
+    )
+    .arg(
+      Arg::with_name("pools")
+        .long("pools")
+        .value_name("HOST:PORT")
+        .help("Comma-separated pools by priority, overrides `pools`")
+        .use_delimiter(true)
+        .global(true),
+    )
+    .arg(
+      Arg::with_name("splits")
+        .long("splits")
+        .value_name("JSON")
+        .help("Mining time split between addresses and pools, overrides `splits`")
+        .global(true),
+    )
+    .arg(
+      Arg::with_name("mode")
+        .long("mode")
+        .value_name("MODE")
+        .help("Mining mode, `nano`, `smart` or `solo`, overrides `mode`")
+        .possible_values(&["nano", "smart", "solo"])
+        .global(true),
+    )
+    .arg(
+      Arg::with_name("name")
+        .long("name")
+        .value_name("NAME")
+        .help("Device name to show in the dashboard, overrides `name`")
+        .global(true),
+    )
+    .arg(
+      Arg::with_name("device_id")
+        .long("device-id")
+        .value_name("ID")
+        .help("Pool device id, overrides `device_id` and the stored id")
+        .global(true),
+    )
+    .arg(
+      Arg::with_name("hashrate")
+        .long("hashrate")
+        .value_name("KHS")
+        .help("Expected hashrate in kH/s instead of the measured one, overrides `hashrate`")
+        .global(true),
+    )
+    .arg(
+      Arg::with_name("devices")
+        .long("devices")
+        .value_name("INDICES")
+        .help("Comma-separated GPU devices to use, overrides `devices`")
+        .use_delimiter(true)
+        .global(true),
+    )
+    .arg(
+      Arg::with_name("memory")
+        .long("memory")
+        .value_name("MB")
+        .help("Comma-separated memory in Mb for each device, overrides `memory`")
+        .use_delimiter(true)
+        .global(true),
+    )
+    .arg(
+      Arg::with_name("profiles")
+        .long("profiles")
+        .value_name("JSON")
+        .help("Device tuning profiles, overrides `profiles`")
+        .global(true),
+    )
+    .arg(
+      Arg::with_name("overrides")
+        .long("overrides")
+        .value_name("JSON")
+        .help("Device tuning by device index, overrides `overrides`")
+        .global(true),
+    )
+    .arg(
+      Arg::with_name("db_path")
+        .long("db-path")
+        .value_name("PATH")
+        .help("Blockchain database directory, overrides `db_path`")
+        .global(true),
+    )
+    .arg(
+      Arg::with_name("db_size")
+        .long("db-size")
+        .value_name("BYTES")
+        .help("Blockchain database size, overrides `db_size`")
+        .global(true),
+    )
+    .arg(
+      Arg::with_name("db_max")
+        .long("db-max")
+        .value_name("COUNT")
+        .help("Maximum number of databases, overrides `db_max`")
+        .global(true),
+    )
+    .arg(
+      Arg::with_name("print-effective-config")
+        .long("print-effective-config")
+        .help("Prints the merged config (file, environment, flags) and exits")
+        .global(true),
+    )
+    .subcommand(SubCommand::with_name("mine").about("Runs the node and mines for the pool (default)"))
+    .subcommand(SubCommand::with_name("devices").about("Lists the available OpenCL devices"))
+    .subcommand(
+      SubCommand::with_name("benchmark")
+        .about("Measures the hashrate of the configured devices")
+        .arg(
+          Arg::with_name("seconds")
+            .long("seconds")
+            .value_name("SECONDS")
+            .help("Duration of the benchmark per device")
+            .default_value("30"),
+        ),
+    )
+    .subcommand(SubCommand::with_name("payout").about("Requests a payout of the confirmed pool balance"))
+    .subcommand(SubCommand::with_name("verify").about("Checks GPU shares against the CPU hash implementation"))
+    .subcommand(
+      SubCommand::with_name("config")
+        .about("Config file utilities")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+          SubCommand::with_name("check").about("Loads the config file and checks it against the available devices"),
+        ),
+    )
+}
+
+/// Builds the effective config: defaults, then the config file, then
+/// `PLUTONIUM_*` environment variables, then command-line flags.
+fn load_config(args: &ArgMatches) -> Result<PoolConfig, Error> {
+  let (config, errors) = merge_config(args)?;
+
+  if errors.is_empty() {
+    Ok(config)
+  } else {
+    Err(Error::Invalid(errors))
+  }
+}
+
+// The effective config and the invalid keys of every source, invalid values
+// are skipped.
+fn merge_config(args: &ArgMatches) -> Result<(PoolConfig, Vec<(String, String)>), Error> {
+  let path: &str = args.value_of("config").unwrap_or(DEFAULT_CONFIG_PATH);
+
+  let (mut config, mut errors) = match PoolConfig::load_partial(path) {
+    // Everything may come from the environment and flags instead
+    Err(Error::ConfigFile(_, ref error))
+      if error.kind() == ErrorKind::NotFound && args.occurrences_of("config") == 0 =>
+    {
+      (PoolConfig::default(), Vec::new())
+    }
+    result => result?,
+  };
+
+  collect(&mut errors, config.merge_env())?;
+
+  for key in CONFIG_KEYS.iter() {
+    if let Some(values) = args.values_of(key) {
+      let values: Vec<&str> = values.collect();
+      let flag: String = format!("--{}", key.replace('_', "-"));
+
+      collect(&mut errors, config.set(key, &values.join(","), &flag))?;
+    }
+  }
+
+  // The only flag without a value
+  if args.is_present("tls_insecure") {
+    config.tls_insecure = true;
+  }
+
+  Ok((config, errors))
+}
+
+// Adds the config problems of `result` to `errors`, other errors are returned.
+fn collect(errors: &mut Vec<(String, String)>, result: Result<(), Error>) -> Result<(), Error> {
+  match result {
+    Ok(()) => {}
+    Err(Error::Config(key, message)) => errors.push((key, message)),
+    Err(Error::Invalid(invalid)) => errors.extend(invalid),
+    Err(error) => Err(error)?,
+  }
+
+  Ok(())
+}
+
+/// Returns the matches of the innermost subcommand.
+fn leaf<'a, 'b>(matches: &'b ArgMatches<'a>) -> &'b ArgMatches<'a> {
+  match matches.subcommand() {
+    (_, Some(args)) => leaf(args),
+    (_, None) => matches,
+  }
+}
+
+fn print_config(config: PoolConfig) -> Result<(), Error> {
+  println!("{}", to_string_pretty(&config)?);
+
+  Ok(())
+}
+
+fn parse_arg<T: FromStr>(name: &str, value: &str) -> Result<T, Error> {
+  value
+    .trim()
+    .parse()
+    .map_err(|_| Error::Config(format!("--{}", name), format!("invalid value `{}`", value)))
+}
+
+fn devices() -> Result<(), Error> {
+  let devices: Vec<Device> = unsafe { get_devices()? };
+
+  println!("[{}] Found {} GPU device(s)", TAG, devices.len());
+
+  for device in devices {
+    println!("[{}] Device #{}", TAG, device.index);
+    println!(
+      "[{}] - Platform       = {} ({})",
+      TAG, device.platform_name, device.platform_vendor
+    );
+    println!("[{}] - Name           = {}", TAG, device.device_name);
+    println!("[{}] - Vendor         = {}", TAG, device.device_vendor);
+    println!("[{}] - Driver         = {}", TAG, device.driver_version);
+    println!("[{}] - OpenCL         = {}", TAG, device.device_version);
+    println!(
+      "[{}] - {} Compute Units @ {} MHz",
+      TAG, device.max_compute_units, device.max_clock_frequency
+    );
+    println!("[{}] - Max Allocation = {} MB", TAG, device.max_mem_alloc_size / ONE_MB);
+    println!("[{}] - Global Memory  = {} MB", TAG, device.global_mem_size / ONE_MB);
+  }
+
+  Ok(())
+}
+
+fn benchmark(config: PoolConfig, seconds: u64) -> Result<(), Error> {
+  let info: &NetworkInfo = config.network.info();
+  let mut miner: Miner = Miner::with_config(MinerConfig::from(&config));
+
+  miner.initialize()?;
+
+  println!(
+    "[{}] Benchmarking {} device(s) for {}s each",
+    TAG,
+    miner.workers().len(),
+    seconds
+  );
+
+  let hashrates: Vec<(u32, f64)> = miner.benchmark(&info.genesis_block, Duration::from_secs(seconds))?;
+
+  for (index, hashrate) in hashrates.iter() {
+    println!("[{}] - GPU{} = {:.2} kH/s", TAG, index, hashrate / 1000.0);
+  }
+
+  let total: f64 = hashrates.iter().map(|(_, hashrate)| hashrate).sum();
+
+  println!("[{}] - Total = {:.2} kH/s", TAG, total / 1000.0);
+
+  unsafe { miner.release()? };
+
+  Ok(())
+}
+
+fn verify(config: PoolConfig) -> Result<(), Error> {
+  let info: &NetworkInfo = config.network.info();
+  let mut miner: Miner = Miner::with_config(MinerConfig::from(&config));
+
+  miner.initialize()?;
 
-  // TODO: Handle error
-  let env: Environment = LmdbEnvironment::new(config.db_path, config.db_size, config.db_max, Flags::empty()).unwrap();
+  let results: Vec<(u32, Option<bool>)> = miner.verify(&info.genesis_block, VERIFY_COMPACT, VERIFY_RUNS)?;
+
+  unsafe { miner.release()? };
+
+  let mut failed: bool = false;
+
+  for (index, result) in results {
+    match result {
+      Some(true) => println!("[{}] - GPU{} = OK", TAG, index),
+      Some(false) => {
+        failed = true;
+        eprintln!("[{}] - GPU{} = Invalid share", TAG, index);
+      }
+      None => {
+        failed = true;
+        eprintln!("[{}] - GPU{} = No share found", TAG, index);
+      }
+    }
+  }
+
+  if failed {
+    Err("GPU verification failed")?
+  }
+
+  Ok(())
+}
+
+/// Connects to the pool and requests a payout once the initial settings and
+/// balance arrived. The following balance confirms the request. Fails if the
+/// connection is lost or the pool does not confirm within `PAYOUT_TIMEOUT`.
+fn payout(config: PoolConfig) -> Result<(), Error> {
+  let pair: KeyPair = config
+    .key_pair()?
+    .ok_or_else(|| Error::Config("payout_key".into(), "required for payout requests".into()))?;
+
+  // The pool with the highest priority
+  let client: PoolClient = PoolClient::new(
+    &config,
+    &config.endpoints()[0],
+    DeviceData::new(&config, Vec::new(), None),
+  )?;
+  let mut nonce: Option<u64> = None;
+  let mut requested: bool = false;
+
+  // The client reconnects forever, end the messages with an error instead
+  let (abort, aborted) = unbounded::<String>();
+
+  {
+    let abort = abort.clone();
+
+    client.notifier.write().unwrap().register(move |event: &PoolEvent| {
+      if let PoolEvent::Disconnected(reason) = event {
+        let _ = abort.unbounded_send(format!("Pool connection failed: {}", reason));
+      }
+    });
+  }
+
+  spawn(move || {
+    sleep(PAYOUT_TIMEOUT);
+
+    let _ = abort.unbounded_send("Payout request timed out".into());
+  });
+
+  let aborted = aborted
+    .map_err(|_| Error::Disconnected)
+    .and_then(|reason: String| -> Result<PoolMessage, Error> { Err(reason.into()) });
+
+  println!("[{}] Connecting to {}", TAG, config.endpoints()[0]);
+
+  for message in client.clone().select(aborted).wait() {
+    match message? {
+      PoolMessage::Settings { nonce: value, .. } => {
+        nonce = Some(value);
+      }
+      PoolMessage::Balance { confirmed, payout, .. } => {
+        if requested {
+          if payout {
+            println!("[{}] Payout request confirmed", TAG);
+            return Ok(());
+          }
+
+          Err("Payout request not active")?
+        }
+
+        if let Some(nonce) = nonce {
+          println!("[{}] Requesting payout of {} (confirmed)", TAG, confirmed);
+
+          client.send(&PoolMessage::payout(&pair, nonce))?;
+          requested = true;
+        }
+      }
+      PoolMessage::Error { reason } => Err(format!("Pool Error: {}", reason))?,
+      _ => {}
+    }
+  }
+
+  Err("Pool connection closed".into())
+}
+
+fn print_shares(report: &ShareReport) {
+  let line = |name: &str, totals: &ShareTotals| {
+    println!(
+      "[{}] - {} = {} found, {} accepted ({:.1}%), {} rejected, {} stale, {} duplicate",
+      TAG,
+      name,
+      totals.found,
+      totals.accepted,
+      totals.accepted_ratio() * 100.0,
+      totals.rejected_total(),
+      totals.stale,
+      totals.duplicate
+    );
+
+    for (reason, count) in totals.rejected.iter() {
+      println!("[{}]   - {} x {}", TAG, count, reason);
+    }
+  };
+
+  println!("[{}] Shares", TAG);
+
+  line("total", &report.total);
+
+  for (index, totals) in report.devices.iter() {
+    line(&format!("GPU{}", index), totals);
+  }
+
+  for (pool, totals) in report.pools.iter() {
+    line(pool, totals);
+  }
+
+  for (pool, count) in report.errors.iter() {
+    println!("[{}] - {} = {} errors not about a share", TAG, pool, count);
+  }
+}
+
+fn pools(config: &PoolConfig) -> String {
+  let endpoints: Vec<String> = config.endpoints().iter().map(ToString::to_string).collect();
+
+  endpoints.join(", ")
+}
+
+fn check(config: PoolConfig) -> Result<(), Error> {
+  println!("[{}] Config OK", TAG);
+  println!("[{}] - network  = {}", TAG, config.network);
+  println!("[{}] - address  = {}", TAG, config.address);
+  println!("[{}] - pools    = {}", TAG, pools(&config));
+  println!("[{}] - mode     = {}", TAG, config.mode);
+  println!("[{}] - name     = {}", TAG, config.name);
+  match config.hashrate {
+    Some(hashrate) => println!("[{}] - hashrate = {} kH/s", TAG, hashrate),
+    None => println!("[{}] - hashrate = measured", TAG),
+  }
+  println!("[{}] - devices  = {:?}", TAG, config.devices);
+  println!("[{}] - memory   = {:?}", TAG, config.memory);
+
+  Ok(())
+}
+
+fn mine(args: ArgMatches<'static>) -> Result<(), Error> {
+  let config: PoolConfig = load_valid_config(&args)?;
+
+  let db_path: String = config.db_path();
+  let db_error = |error: String| Error::Config("db_path".into(), format!("cannot open `{}`: {}", db_path, error));
+
+  create_dir_all(&db_path).map_err(|error| db_error(error.to_string()))?;
+
+  let env: Environment = LmdbEnvironment::new(&db_path, config.db_size, config.db_max, Flags::empty())
+    .map_err(|error| db_error(format!("{:?}", error)))?;
 
   let env: &'static Environment = Box::leak(Box::from(env));
   let mut builder: ClientBuilder = ClientBuilder::new(Protocol::Ws, env); // TODO: Protocol::Dumb?
-  let info: &NetworkInfo = get_network_info(NetworkId::Main).unwrap();
+  let info: &NetworkInfo = config.network.info();
 
   let seeds: Vec<Seed> = info
     .seed_peers
@@ -58,8 +689,8 @@ fn main() -> Result<(), Error> {
     .map(Seed::Peer)
     .collect();
 
-  builder.with_network_id(NetworkId::Main);
-  builder.with_hostname(config.host);
+  builder.with_network_id(config.network.id());
+  builder.with_hostname(&config.host);
   builder.with_port(config.port);
   builder.with_seeds(seeds);
 
@@ -69,22 +700,36 @@ fn main() -> Result<(), Error> {
   let blockchain: Arc<Blockchain<'static>> = Arc::clone(&consensus.blockchain);
   let network: Arc<Network> = Arc::clone(&consensus.network);
 
-  // const deviceId = Nimiq.BasePoolMiner.generateDeviceId(networkConfig);
-  // TODO: Handle error
-  let address: Address = Address::from_user_friendly_address(config.address).unwrap();
+  if config.mode == PoolMode::Solo {
+    return solo(args, config, client);
+  }
+
+  let address: Address = Address::from_user_friendly_address(&config.address)
+    .map_err(|error| Error::Config("address".into(), format!("{:?}", error)))?;
   let peer: PeerAddress = network.network_config.peer_address();
   let pair: &KeyPair = network.network_config.key_pair();
 
+  // Derived from the peer key on the first run, like `BasePoolMiner.generateDeviceId`
+  let device: u32 = match config.device_id {
+    Some(device) => device,
+    None => load_device_id(&db_path, Some(&pair.private))?,
+  };
+
   println!("[{}] Sushipool {} starting", TAG, MINER_VERSION);
-  println!("[{}] - pool server  = {}:{}", TAG, config.host, config.port);
+  println!("[{}] - pool server  = {}", TAG, pools(&config));
   println!("[{}] - peer address = {}", TAG, peer);
   println!("[{}] - peer pub key = {}", TAG, pair.public.to_hex());
-  println!("[{}] - device       = {}", TAG, config.name);
+  println!("[{}] - device       = {} ({})", TAG, config.name, device);
   println!("[{}] - address      = {}", TAG, address.to_user_friendly_address());
 
-  let host = config.host;
+  let host: String = config.endpoints()[0].to_string();
 
-  let miner: PoolMiner = PoolMiner::new(Arc::clone(&blockchain), Arc::clone(&network), config);
+  let miner: PoolMiner = PoolMiner::new(
+    Arc::clone(&blockchain),
+    Arc::clone(&network),
+    Arc::clone(&consensus.mempool),
+    config,
+  )?;
 
   let miner: Arc<RwLock<PoolMiner>> = Arc::new(RwLock::new(miner));
 
@@ -150,6 +795,7 @@ fn main() -> Result<(), Error> {
   //
 
   {
+    let miner: Arc<RwLock<PoolMiner>> = Arc::clone(&miner);
     let chain: Arc<Blockchain<'static>> = Arc::clone(&blockchain);
 
     blockchain.notifier.write().register(move |event: &BlockchainEvent| {
@@ -171,6 +817,8 @@ fn main() -> Result<(), Error> {
           }
         }
       }
+
+      miner.write().unwrap().head_changed();
     });
   }
 
@@ -196,6 +844,147 @@ fn main() -> Result<(), Error> {
   //   });
   // }
 
+  //
+  // Pool Events
+  //
+
+  {
+    let client: PoolClient = miner.read().unwrap().client();
+    let miner: Arc<RwLock<PoolMiner>> = Arc::clone(&miner);
+
+    client.notifier.write().unwrap().register(move |event: &PoolEvent| {
+      miner.write().unwrap().pool_event(event);
+    });
+  }
+
+  //
+  // Pool Failback
+  //
+
+  {
+    let miner: Arc<RwLock<PoolMiner>> = Arc::clone(&miner);
+
+    spawn(move || loop {
+      sleep(PROBE_INTERVAL);
+
+      // Probe without holding the miner lock
+      let preferred: Vec<(usize, PoolEndpoint)> = miner.read().unwrap().preferred_pools();
+      let proxy: Option<PoolProxy> = miner.read().unwrap().proxy();
+
+      if let Some((index, endpoint)) = preferred
+        .into_iter()
+        .find(|(_, endpoint)| endpoint.is_reachable(proxy.as_ref(), PROBE_TIMEOUT))
+      {
+        println!("[{}] Pool {} is reachable again", TAG, endpoint);
+
+        if let Err(error) = miner.write().unwrap().switch_pool(index) {
+          eprintln!("[{}] Failback Error: {:?}", TAG, error);
+        }
+      }
+    });
+  }
+
+  //
+  // Pool Splits
+  //
+
+  {
+    let miner: Arc<RwLock<PoolMiner>> = Arc::clone(&miner);
+
+    spawn(move || {
+      let mut last: Instant = Instant::now();
+
+      loop {
+        sleep(SPLIT_INTERVAL);
+
+        let now: Instant = Instant::now();
+
+        if let Err(error) = miner.write().unwrap().rotate_split(now - last) {
+          eprintln!("[{}] Split Error: {:?}", TAG, error);
+        }
+
+        last = now;
+      }
+    });
+  }
+
+  //
+  // Mempool Changes
+  //
+
+  {
+    let miner: Arc<RwLock<PoolMiner>> = Arc::clone(&miner);
+
+    spawn(move || loop {
+      sleep(MEMPOOL_INTERVAL);
+
+      miner.write().unwrap().mempool_changed();
+    });
+  }
+
+  //
+  // Share Difficulty and Report
+  //
+
+  {
+    let miner: Arc<RwLock<PoolMiner>> = Arc::clone(&miner);
+
+    spawn(move || loop {
+      sleep(DIFFICULTY_INTERVAL);
+
+      if let Err(error) = miner.write().unwrap().check_difficulty() {
+        eprintln!("[{}] Difficulty Error: {:?}", TAG, error);
+      }
+
+      print_shares(&miner.read().unwrap().share_report());
+    });
+  }
+
+  //
+  // Payout Requests
+  //
+
+  {
+    let miner: Arc<RwLock<PoolMiner>> = Arc::clone(&miner);
+    let signals: Signals = Signals::new(&[SIGUSR1])?;
+
+    spawn(move || {
+      for _ in signals.forever() {
+        println!("[{}] SIGUSR1 received, requesting payout", TAG);
+
+        if let Err(error) = miner.write().unwrap().request_payout() {
+          eprintln!("[{}] Payout request failed", TAG);
+
+          report(&error);
+        }
+      }
+    });
+  }
+
+  //
+  // Config Reload
+  //
+
+  {
+    let miner: Arc<RwLock<PoolMiner>> = Arc::clone(&miner);
+    let signals: Signals = Signals::new(&[SIGHUP])?;
+
+    spawn(move || {
+      for _ in signals.forever() {
+        println!("[{}] SIGHUP received, reloading config", TAG);
+
+        let result: Result<(), Error> =
+          load_valid_config(&args).and_then(|config| miner.write().unwrap().reload(config));
+
+        if let Err(error) = result {
+          eprintln!("[{}] Config reload failed", TAG);
+
+          report(&error);
+        }
+      }
+    });
+  }
+
   println!("[{}] Connecting to Nimiq network", TAG);
 
   //
@@ -223,3 +1012,121 @@ fn main() -> Result<(), Error> {
 
   Ok(())
 }
+
+/// Mines blocks of the local node to the network target, found blocks are
+/// relayed by the node itself.
+fn solo(args: ArgMatches<'static>, config: PoolConfig, client: ClientInitializeFuture) -> Result<(), Error> {
+  let consensus: Arc<Consensus> = client.consensus();
+
+  let blockchain: Arc<Blockchain<'static>> = Arc::clone(&consensus.blockchain);
+  let network: Arc<Network> = Arc::clone(&consensus.network);
+
+  println!("[{}] Sushipool {} starting solo", TAG, MINER_VERSION);
+  println!("[{}] - peer address = {}", TAG, network.network_config.peer_address());
+  println!("[{}] - device       = {}", TAG, config.name);
+  println!("[{}] - address      = {}", TAG, config.address);
+
+  let miner: SoloMiner = SoloMiner::new(
+    Arc::clone(&blockchain),
+    Arc::clone(&network),
+    Arc::clone(&consensus.mempool),
+    config,
+  )?;
+
+  let miner: Arc<RwLock<SoloMiner>> = Arc::new(RwLock::new(miner));
+
+  //
+  // Consensus Events
+  //
+
+  {
+    let miner: Arc<RwLock<SoloMiner>> = Arc::clone(&miner);
+    let network: Arc<Network> = Arc::clone(&network);
+
+    consensus.notifier.write().register(move |event: &ConsensusEvent| {
+      println!("[{}] Consensus Event: {:?}", TAG, event);
+      println!("[{}] Peers = {}", TAG, network.peer_count());
+
+      match event {
+        ConsensusEvent::Established => {
+          ESTABLISHED.store(true, Ordering::SeqCst);
+
+          miner.write().unwrap().connect();
+        }
+        ConsensusEvent::Lost => {
+          ESTABLISHED.store(false, Ordering::SeqCst);
+
+          miner.write().unwrap().disconnect();
+        }
+        _ => {}
+      }
+    });
+  }
+
+  //
+  // Blockchain Events
+  //
+
+  {
+    let miner: Arc<RwLock<SoloMiner>> = Arc::clone(&miner);
+    let chain: Arc<Blockchain<'static>> = Arc::clone(&blockchain);
+
+    blockchain.notifier.write().register(move |_: &BlockchainEvent| {
+      if chain.height() % 100 == 0 || ESTABLISHED.load(Ordering::Acquire) {
+        println!("[{}] Blockchain Head = {}", TAG, chain.height());
+        println!("[{}] Latest Block = https://nimiq.watch/#{}", TAG, chain.head_hash());
+      }
+
+      miner.write().unwrap().head_changed();
+    });
+  }
+
+  //
+  // Mempool Changes
+  //
+
+  {
+    let miner: Arc<RwLock<SoloMiner>> = Arc::clone(&miner);
+
+    spawn(move || loop {
+      sleep(MEMPOOL_INTERVAL);
+
+      miner.write().unwrap().mempool_changed();
+    });
+  }
+
+  //
+  // Config Reload
+  //
+
+  {
+    let miner: Arc<RwLock<SoloMiner>> = Arc::clone(&miner);
+    let signals: Signals = Signals::new(&[SIGHUP])?;
+
+    spawn(move || {
+      for _ in signals.forever() {
+        println!("[{}] SIGHUP received, reloading config", TAG);
+
+        let result: Result<(), Error> =
+          load_valid_config(&args).and_then(|config| miner.write().unwrap().reload(config));
+
+        if let Err(error) = result {
+          eprintln!("[{}] Config reload failed", TAG);
+
+          report(&error);
+        }
+      }
+    });
+  }
+
+  println!("[{}] Connecting to Nimiq network", TAG);
+
+  let future = client
+    .and_then(|client| client.connect()) // Run Nimiq client
+    .map(|_| println!("[+] Client finished"))
+    .map_err(|error| println!("[x] Client failed: {}", error));
+
+  run(future);
+
+  Ok(())
+}
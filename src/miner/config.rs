@@ -1,18 +1,69 @@
+use std::collections::BTreeMap;
+
 use crate::ffi::cl_uint;
+use crate::miner::Device;
+use crate::miner::DeviceProfile;
+use crate::miner::Tuning;
+use crate::pool::PoolConfig;
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct MinerConfig {
   pub devices: Vec<cl_uint>,
   pub memsizes: Vec<cl_uint>,
+  pub profiles: Vec<DeviceProfile>,
+  pub overrides: BTreeMap<cl_uint, Tuning>,
 }
 
 impl MinerConfig {
   #[inline]
-  pub fn allowed_device(&self, index: usize, gindex: cl_uint) -> bool {
-    if self.devices.is_empty() {
-      return true;
+  pub fn allowed_device(&self, index: cl_uint) -> bool {
+    self.devices.is_empty() || self.devices.contains(&index)
+  }
+
+  /// Returns the configured memory size (in Mb) of the nth enabled device.
+  #[inline]
+  pub fn memory_size(&self, position: usize) -> Option<cl_uint> {
+    if self.memsizes.len() == 1 {
+      Some(self.memsizes[0])
+    } else {
+      self.memsizes.get(position).cloned()
     }
+  }
 
-    self.devices.get(index).map(|&device| device == gindex).unwrap_or(false)
+  /// Resolves the tuning of `device`, the nth enabled device.
+  ///
+  /// Matching profiles are applied in order, followed by the `memory` list
+  /// if one is set and the override for the device index. Memory sizes of 0
+  /// leave the profile's memory in place.
+  pub fn tuning(&self, device: &Device, position: usize) -> Tuning {
+    let mut tuning: Tuning = Tuning::default();
+
+    for profile in self.profiles.iter() {
+      if profile.matcher.is_match(device) {
+        tuning.merge(&profile.tuning);
+      }
+    }
+
+    if let Some(memory) = self.memory_size(position).filter(|&memory| memory > 0) {
+      tuning.memory = Some(memory);
+    }
+
+    if let Some(overrides) = self.overrides.get(&device.index) {
+      tuning.merge(overrides);
+    }
+
+    tuning
+  }
+}
+
+impl<'a> From<&'a PoolConfig> for MinerConfig {
+  #[inline]
+  fn from(config: &'a PoolConfig) -> Self {
+    Self {
+      devices: config.devices.clone(),
+      memsizes: config.memory.clone(),
+      profiles: config.profiles.clone(),
+      overrides: config.overrides.clone(),
+    }
   }
 }
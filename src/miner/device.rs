@@ -0,0 +1,119 @@
+use crate::config::VENDOR_AMD;
+use crate::config::VENDOR_NVIDIA;
+use crate::error::Error;
+use crate::ffi::cl_device_id;
+use crate::ffi::cl_platform_id;
+use crate::ffi::cl_uint;
+use crate::ffi::cl_ulong;
+use crate::ffi::CL_DEVICE_GLOBAL_MEM_SIZE;
+use crate::ffi::CL_DEVICE_MAX_CLOCK_FREQUENCY;
+use crate::ffi::CL_DEVICE_MAX_COMPUTE_UNITS;
+use crate::ffi::CL_DEVICE_MAX_MEM_ALLOC_SIZE;
+use crate::ffi::CL_DEVICE_NAME;
+use crate::ffi::CL_DEVICE_VENDOR;
+use crate::ffi::CL_DEVICE_VERSION;
+use crate::ffi::CL_DRIVER_VERSION;
+use crate::ffi::CL_PLATFORM_NAME;
+use crate::ffi::CL_PLATFORM_VENDOR;
+use crate::opencl::get_device_ids;
+use crate::opencl::get_device_info;
+use crate::opencl::get_platform_ids;
+use crate::opencl::get_platform_info;
+use crate::utils::bytes_into;
+use crate::utils::bytes_into_string;
+
+/// A GPU device as reported by OpenCL.
+#[derive(Clone, Debug)]
+pub struct Device {
+  /// Index of the device across all platforms
+  pub index: cl_uint,
+  pub platform_id: cl_platform_id,
+  pub platform_name: String,
+  pub platform_vendor: String,
+  pub device_id: cl_device_id,
+  pub device_name: String,
+  pub device_vendor: String,
+  pub driver_version: String,
+  pub device_version: String,
+  pub max_compute_units: cl_uint,
+  pub max_clock_frequency: cl_uint,
+  pub max_mem_alloc_size: cl_ulong,
+  pub global_mem_size: cl_ulong,
+}
+
+//
+// Warning: BYO Safety
+//
+unsafe impl Send for Device {}
+unsafe impl Sync for Device {}
+
+impl Device {
+  #[inline]
+  pub fn is_amd(&self) -> bool {
+    self.platform_vendor.starts_with(VENDOR_AMD)
+  }
+
+  #[inline]
+  pub fn is_nvidia(&self) -> bool {
+    self.platform_vendor.starts_with(VENDOR_NVIDIA)
+  }
+}
+
+/// Enumerates the GPU devices of every OpenCL platform.
+pub unsafe fn get_devices() -> Result<Vec<Device>, Error> {
+  let mut devices: Vec<Device> = vec![];
+
+  for platform in get_platform_ids()? {
+    let platform_name: String = get_platform_info(platform, CL_PLATFORM_NAME)?;
+    let platform_vendor: String = get_platform_info(platform, CL_PLATFORM_VENDOR)?;
+
+    for device in get_device_ids(platform)? {
+      // Fetch device info
+      let device_name: Vec<u8> = get_device_info(device, CL_DEVICE_NAME)?;
+      let device_vendor: Vec<u8> = get_device_info(device, CL_DEVICE_VENDOR)?;
+      let driver_version: Vec<u8> = get_device_info(device, CL_DRIVER_VERSION)?;
+      let device_version: Vec<u8> = get_device_info(device, CL_DEVICE_VERSION)?;
+      let max_compute_units: Vec<u8> = get_device_info(device, CL_DEVICE_MAX_COMPUTE_UNITS)?;
+      let max_clock_frequency: Vec<u8> = get_device_info(device, CL_DEVICE_MAX_CLOCK_FREQUENCY)?;
+      let max_mem_alloc_size: Vec<u8> = get_device_info(device, CL_DEVICE_MAX_MEM_ALLOC_SIZE)?;
+      let global_mem_size: Vec<u8> = get_device_info(device, CL_DEVICE_GLOBAL_MEM_SIZE)?;
+
+      // Cast device info to proper types
+      let device_name: String = bytes_into_string(device_name); // TODO: Limit to 255 chars?
+      let device_vendor: String = bytes_into_string(device_vendor); // TODO: Limit to 255 chars?
+      let driver_version: String = bytes_into_string(driver_version); // TODO: Limit to 64 chars?
+      let device_version: String = bytes_into_string(device_version); // TODO: Limit to 64 chars?
+
+      // Set default driver/device versions (unknown)
+      let driver_version: String = if driver_version.is_empty() {
+        String::from("?")
+      } else {
+        driver_version
+      };
+
+      let device_version: String = if device_version.is_empty() {
+        String::from("?")
+      } else {
+        device_version
+      };
+
+      devices.push(Device {
+        index: devices.len() as cl_uint,
+        platform_id: platform,
+        platform_name: platform_name.clone(),
+        platform_vendor: platform_vendor.clone(),
+        device_id: device,
+        device_name,
+        device_vendor,
+        driver_version,
+        device_version,
+        max_compute_units: bytes_into(max_compute_units)?,
+        max_clock_frequency: bytes_into(max_clock_frequency)?,
+        max_mem_alloc_size: bytes_into(max_mem_alloc_size)?,
+        global_mem_size: bytes_into(global_mem_size)?,
+      });
+    }
+  }
+
+  Ok(devices)
+}
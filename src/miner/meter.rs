@@ -0,0 +1,79 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+use std::time::Instant;
+
+// Hashrates are averaged over this window
+const WINDOW: Duration = Duration::from_secs(2 * 60);
+
+#[derive(Clone, Copy, Debug)]
+struct Sample {
+  device: u32,
+  at: Instant,
+  hashes: u64,
+  elapsed: Duration,
+}
+
+/// Hashrates measured from the completed runs of the GPU workers.
+#[derive(Debug, Default)]
+pub struct HashMeter {
+  samples: VecDeque<Sample>,
+}
+
+impl HashMeter {
+  /// Records a run of `device` that hashed `hashes` nonces in `elapsed`.
+  pub fn record(&mut self, device: u32, hashes: u64, elapsed: Duration) {
+    let now: Instant = Instant::now();
+
+    self.samples.push_back(Sample {
+      device,
+      at: now,
+      hashes,
+      elapsed,
+    });
+
+    while let Some(sample) = self.samples.front() {
+      if now.duration_since(sample.at) > WINDOW {
+        self.samples.pop_front();
+      } else {
+        break;
+      }
+    }
+  }
+
+  /// Returns the hashrate (H/s) of `device` while it was running.
+  pub fn device(&self, device: u32) -> Option<f64> {
+    let (hashes, elapsed) = self
+      .samples
+      .iter()
+      .filter(|sample| sample.device == device)
+      .fold((0, Duration::from_secs(0)), |(hashes, elapsed), sample| {
+        (hashes + sample.hashes, elapsed + sample.elapsed)
+      });
+
+    rate(hashes, elapsed)
+  }
+
+  /// Returns the hashrate (H/s) of the rig, ie. all hashes over the time
+  /// since the first run in the window, or `None` before the first run.
+  pub fn total(&self) -> Option<f64> {
+    let first: &Sample = self.samples.front()?;
+    let hashes: u64 = self.samples.iter().map(|sample| sample.hashes).sum();
+
+    rate(hashes, first.elapsed + first.at.elapsed())
+  }
+
+  /// Forgets all runs, eg. after the workers were rebuilt.
+  pub fn clear(&mut self) {
+    self.samples.clear();
+  }
+}
+
+fn rate(hashes: u64, elapsed: Duration) -> Option<f64> {
+  let seconds: f64 = elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) / 1_000_000_000.0;
+
+  if hashes > 0 && seconds > 0.0 {
+    Some(hashes as f64 / seconds)
+  } else {
+    None
+  }
+}
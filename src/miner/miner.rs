@@ -1,9 +1,10 @@
-use base64::encode;
 use beserial::Serialize;
 use byteorder::ByteOrder;
 use byteorder::LittleEndian;
 use nimiq_block::Block;
 use nimiq_block::BlockHeader;
+use nimiq_block::Target;
+use nimiq_block::TargetCompact;
 use nimiq_blockchain::Blockchain;
 use nimiq_hash::Argon2dHash;
 use nimiq_hash::Blake2bHash;
@@ -12,8 +13,13 @@ use std::mem::size_of;
 use std::os::raw::c_char;
 use std::os::raw::c_void;
 use std::ptr::null;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::sync::Mutex;
 use std::sync::RwLock;
+use std::time::Duration;
+use std::time::Instant;
 
 use crate::config::ARGON2_BLOCK_SIZE;
 use crate::config::ARGON2_HASH_LENGTH;
@@ -28,14 +34,16 @@ use crate::config::NONCES_PER_GROUP;
 use crate::config::ONE_GB;
 use crate::config::ONE_MB;
 use crate::config::THREADS_PER_LANE;
-use crate::config::VENDOR_AMD;
-use crate::config::VENDOR_NVIDIA;
 use crate::error::Error;
 use crate::ffi::*;
 use crate::hash::Source;
 use crate::hash::ARGON2D_CL;
 use crate::hash::BLAKE2B_CL;
+use crate::miner::get_devices;
+use crate::miner::Device;
+use crate::miner::HashMeter;
 use crate::miner::MinerConfig;
+use crate::miner::Tuning;
 use crate::miner::Worker;
 use crate::opencl::build_program;
 use crate::opencl::create_buffer;
@@ -43,14 +51,7 @@ use crate::opencl::create_command_queue;
 use crate::opencl::create_context;
 use crate::opencl::create_kernel;
 use crate::opencl::create_program_with_source;
-use crate::opencl::get_device_ids;
-use crate::opencl::get_device_info;
-use crate::opencl::get_platform_ids;
-use crate::opencl::get_platform_info;
 use crate::opencl::set_kernel_arg;
-use crate::pool::PoolMessage;
-use crate::utils::bytes_into;
-use crate::utils::bytes_into_string;
 
 type Seed = [u8; INITIAL_SEED_SIZE];
 type ArcChain = Arc<Blockchain<'static>>;
@@ -59,14 +60,13 @@ static ARGON2_MEMORY_COST: u64 = _ARGON2_MEMORY_COST as u64;
 static BLOCK_HEADER_LEN: u32 = 146;
 static MAX_NONCE: usize = 4294967296; // 2 ** 32
 
-lazy_static! {
-  pub static ref MINER: Arc<RwLock<Miner>> = {
-    let mut miner = Miner::new();
-
-    miner.initialize().unwrap();
+// Bumped whenever the current work is outdated
+static WORK_ID: AtomicUsize = AtomicUsize::new(0);
 
-    Arc::new(RwLock::new(miner))
-  };
+lazy_static! {
+  pub static ref MINER: Arc<RwLock<Miner>> = Arc::new(RwLock::new(Miner::new()));
+  // Outside of `MINER`, which is locked while mining
+  pub static ref HASH_METER: Mutex<HashMeter> = Mutex::new(HashMeter::default());
   static ref SEED: Seed = {
     let mut seed: Seed = [0; INITIAL_SEED_SIZE];
     let salt: &[u8] = ARGON2_SALT.as_bytes();
@@ -87,6 +87,16 @@ lazy_static! {
   static ref SOURCES: [Source; 2] = { [Source::new(ARGON2D_CL), Source::new(BLAKE2B_CL)] };
 }
 
+/// A nonce found by a GPU worker.
+#[derive(Debug)]
+pub enum Found {
+  /// A share on top of the current head, the block has its nonce set
+  Share(Block),
+  /// A share with `nonce` of outdated work, ie. the work changed or the
+  /// block no longer builds on the head
+  Stale(u32),
+}
+
 // #[derive(Debug)]
 #[repr(C)]
 pub struct Miner {
@@ -108,12 +118,14 @@ unsafe impl Send for Miner {}
 unsafe impl Sync for Miner {}
 
 impl Miner {
+  #[inline]
   pub fn new() -> Self {
+    Self::with_config(MinerConfig::default())
+  }
+
+  pub fn with_config(config: MinerConfig) -> Self {
     Self {
-      config: MinerConfig {
-        devices: vec![],
-        memsizes: vec![],
-      },
+      config,
       nonce: 0,
       workid: 0,
       scompact: 0,
@@ -125,8 +137,16 @@ impl Miner {
     }
   }
 
-  pub fn mine(&mut self, block: Block, blockchain: ArcChain) -> Result<(), Error> {
-    self.workid += 1;
+  /// Aborts the work of a running `mine` call after its current run.
+  #[inline]
+  pub fn interrupt() {
+    WORK_ID.fetch_add(1, Ordering::SeqCst);
+  }
+
+  /// Mines `block` until it is outdated, passing every share found to `share`
+  /// along with the index of the device that found it.
+  pub fn mine<F: FnMut(u32, Found)>(&mut self, block: Block, blockchain: ArcChain, mut share: F) -> Result<(), Error> {
+    self.workid = (WORK_ID.fetch_add(1, Ordering::SeqCst) + 1) as cl_uint;
     self.nonce = 0;
     self.seed = self._seed(&block.header);
 
@@ -139,13 +159,23 @@ impl Miner {
 
         'mine: loop {
           let nonce = self.nonce;
+          let start: Instant = Instant::now();
 
           self.nonce += nonces;
 
           let nnonce = worker.mine(&nonce, &self.scompact, self.zero_ptr())?;
 
+          HASH_METER
+            .lock()
+            .unwrap()
+            .record(worker.device_index, u64::from(nonces), start.elapsed());
+
           // Another block arrived
-          if workid != self.workid {
+          if workid != WORK_ID.load(Ordering::SeqCst) as cl_uint {
+            if nnonce > 0 {
+              share(worker.device_index, Found::Stale(nnonce));
+            }
+
             break 'mine;
           }
 
@@ -171,50 +201,13 @@ impl Miner {
                 } else {
                   println!("Invalid Block POF: {}", block.header.pow());
                 }
-              } else {
-                println!("[+] Invalid Block Body: {}", block.header.pow());
               }
-            // if (!this._submittingBlock) {
-            //   block.header.nonce = nnonce;
-
-            //   let blockValid = false;
-            //   if block.isFull() && BlockUtils.isProofOfWork(hash, block.target) {
-            //     this._submittingBlock = true;
-            //     if (await block.header.verifyProofOfWork()) {
-            //       this._numBlocksMined++;
-            //       blockValid = true;
-
-            //       // Tell listeners that we've mined a block.
-            //       this.fire('block-mined', block, this);
-
-            //       // Push block into blockchain.
-            //       if ((await this._blockchain.pushBlock(block)) < 0) {
-            //         this._submittingBlock = false;
-            //         this._startWork().catch(Log.w.tag(Miner));
-            //         return;
-            //       } else {
-            //         this._submittingBlock = false;
-            //       }
-            //     } else {
-            //       Log.d(Miner, ``);
-            //     }
-            //   }
-
-            //   this.fire('share', block, blockValid, this);
-            // }
-
-            // let share = PoolMessage::ShareNano {
-            //   block: encode(&block.serialize_to_vec()),
-            // };
-
-            // println!("Pool Share: {:?}", share);
-
-            // this._send({
-            //   message: 'share',
-            //   block: BufferUtils.toBase64(block.serialize())
-            // });
+
+              share(worker.device_index, Found::Share(block));
             } else {
               println!("[x] Invalid Share: {}/{}", nnonce, block.header.pow());
+
+              share(worker.device_index, Found::Stale(nnonce));
             }
           }
 
@@ -228,6 +221,75 @@ impl Miner {
     Ok(())
   }
 
+  /// Hashes `block` on every worker for `duration` and returns the measured
+  /// hashrate (H/s) of each device.
+  pub fn benchmark(&mut self, block: &Block, duration: Duration) -> Result<Vec<(cl_uint, f64)>, Error> {
+    let scompact: cl_uint = block.header.n_bits.clone().into();
+    let mut hashrates: Vec<(cl_uint, f64)> = Vec::with_capacity(self.workers.len());
+
+    self.seed = self._seed(&block.header);
+
+    for worker in &self.workers {
+      let start: Instant = Instant::now();
+      let mut nonce: cl_uint = 0;
+      let mut hashes: u64 = 0;
+
+      unsafe {
+        worker.setup(self.seed_ptr(), self.zero_ptr())?;
+
+        while start.elapsed() < duration {
+          worker.mine(&nonce, &scompact, self.zero_ptr())?;
+
+          nonce = nonce.wrapping_add(worker.nonces_per_run);
+          hashes += u64::from(worker.nonces_per_run);
+        }
+      }
+
+      hashrates.push((worker.device_index, hashes as f64 / seconds(start.elapsed())));
+    }
+
+    Ok(hashrates)
+  }
+
+  /// Mines `block` against the share target `scompact` and checks the first
+  /// nonce found by each worker with the CPU implementation of Argon2d.
+  ///
+  /// Returns `None` for a device if it found no share within `runs` runs.
+  pub fn verify(&mut self, block: &Block, scompact: u32, runs: usize) -> Result<Vec<(cl_uint, Option<bool>)>, Error> {
+    let target: Target = TargetCompact::from(scompact).into();
+    let mut results: Vec<(cl_uint, Option<bool>)> = Vec::with_capacity(self.workers.len());
+
+    self.seed = self._seed(&block.header);
+
+    for worker in &self.workers {
+      let mut nonce: cl_uint = 0;
+      let mut result: Option<bool> = None;
+
+      unsafe {
+        worker.setup(self.seed_ptr(), self.zero_ptr())?;
+
+        for _ in 0..runs {
+          let nnonce: cl_uint = worker.mine(&nonce, &scompact, self.zero_ptr())?;
+
+          nonce = nonce.wrapping_add(worker.nonces_per_run);
+
+          if nnonce > 0 {
+            let mut header: BlockHeader = block.header.clone();
+
+            header.nonce = nnonce;
+            result = Some(target.is_met_by(&header.pow()));
+
+            break;
+          }
+        }
+      }
+
+      results.push((worker.device_index, result));
+    }
+
+    Ok(results)
+  }
+
   #[inline]
   pub fn workers(&self) -> &[Worker] {
     &self.workers
@@ -238,6 +300,76 @@ impl Miner {
     &mut self.workers
   }
 
+  #[inline]
+  pub fn configure(&mut self, config: MinerConfig) {
+    self.config = config;
+  }
+
+  /// Applies `config` to initialized workers. Only workers of devices that
+  /// were removed or got a different memory size are released and rebuilt,
+  /// the replaced ones before their devices allocate memory again. If a worker
+  /// cannot be built, all workers are released.
+  pub fn reconfigure(&mut self, config: MinerConfig) -> Result<(), Error> {
+    let devices: Vec<Device> = unsafe { get_devices()? };
+    let mut current: Vec<Worker> = self.workers.drain(..).collect();
+    let mut plan: Vec<(&Device, Tuning, Option<Worker>)> = Vec::new();
+
+    for device in devices.iter() {
+      if !config.allowed_device(device.index) {
+        continue;
+      }
+
+      let tuning: Tuning = config.tuning(device, plan.len());
+
+      let position: Option<usize> = current
+        .iter()
+        .position(|worker| worker.device_index == device.index && worker.tuning == tuning);
+
+      plan.push((device, tuning, position.map(|position| current.remove(position))));
+    }
+
+    for worker in current {
+      println!("[+] Releasing Device #{}", worker.device_index);
+
+      release_worker(worker);
+    }
+
+    let mut plan = plan.into_iter();
+
+    while let Some((device, tuning, worker)) = plan.next() {
+      if let Some(worker) = worker {
+        self.workers.push(worker);
+        continue;
+      }
+
+      println!("[+] Rebuilding Device #{}", device.index);
+
+      match unsafe { create_worker(device, tuning) } {
+        Ok(worker) => self.workers.push(worker),
+        Err(error) => {
+          let kept = plan.by_ref().filter_map(|(_, _, worker)| worker);
+
+          for worker in self.workers.drain(..).chain(kept) {
+            release_worker(worker);
+          }
+
+          Err(error)?
+        }
+      }
+    }
+
+    self.config = config;
+
+    if self.workers.is_empty() {
+      Err(Error::OpenCL(
+        CL_DEVICE_NOT_FOUND,
+        "Failed to find any usable GPU devices.",
+      ))?
+    }
+
+    Ok(())
+  }
+
   #[inline]
   pub fn initialize(&mut self) -> Result<(), Error> {
     self.workers = unsafe { initialize(&self.config)? };
@@ -284,6 +416,11 @@ impl Miner {
   }
 }
 
+#[inline]
+fn seconds(duration: Duration) -> f64 {
+  duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1_000_000_000.0
+}
+
 impl std::fmt::Debug for Miner {
   fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
     f.debug_struct("Miner")
@@ -297,213 +434,32 @@ impl std::fmt::Debug for Miner {
 }
 
 unsafe fn initialize(config: &MinerConfig) -> Result<Vec<Worker>, Error> {
-  // Find all OpenCL platforms
-  let platforms: Vec<cl_platform_id> = get_platform_ids()?;
+  // Find all OpenCL GPU devices
+  let devices: Vec<Device> = get_devices()?;
 
-  println!("[+] Platforms");
-  println!("[+] - Total    = {}", platforms.len());
-  println!("[+] - Pointers = {:?}", platforms);
+  println!("[+] Devices");
+  println!("[+] - Total    = {}", devices.len());
 
   let mut workers: Vec<Worker> = vec![];
 
-  'platform: for platform in platforms.into_iter() {
-    let name: String = get_platform_info(platform, CL_PLATFORM_NAME)?;
-    let vendor: String = get_platform_info(platform, CL_PLATFORM_VENDOR)?;
-    let is_amd: bool = vendor.starts_with(VENDOR_AMD);
-    let is_nvidia: bool = vendor.starts_with(VENDOR_NVIDIA);
-
-    println!("[+] Platform");
-    println!("[+] - Name   = {}", name);
-    println!("[+] - Vendor = {}", vendor);
-    println!("[+] - AMD    = {}", is_amd);
-    println!("[+] - Nvidia = {}", is_nvidia);
-
-    if !is_amd && !is_nvidia {
-      eprintln!("[x] Unsupported Platform: {}/{}", name, vendor);
-      // continue 'platform;
+  // Iterate over devices, setup workers
+  'device: for device in devices.iter() {
+    // Check if this device is allowed
+    if !config.allowed_device(device.index) {
+      println!("[+] Device #{} Disabled", device.index);
+      continue 'device;
     }
 
-    // Find all GPU devices
-    let devices: Vec<cl_device_id> = get_device_ids(platform)?;
-
-    if devices.is_empty() {
-      eprintln!("[x] No GPU Devices Found");
-      continue 'platform;
+    if !device.is_amd() && !device.is_nvidia() {
+      eprintln!(
+        "[x] Unsupported Platform: {}/{}",
+        device.platform_name, device.platform_vendor
+      );
     }
 
-    println!("[+] Devices");
-    println!("[+] - Total    = {}", devices.len());
-    println!("[+] - Pointers = {:?}", devices);
+    let tuning: Tuning = config.tuning(device, workers.len());
 
-    // Iterate over devices, setup workers
-    'device: for (index, device) in devices.into_iter().enumerate() {
-      let gindex: cl_uint = workers.len() as cl_uint + 1;
-
-      // Check if this device is allowed
-      if !config.allowed_device(index, gindex) {
-        println!("[+] Device #{} Disabled", gindex);
-        continue 'device;
-      }
-
-      // Fetch device info
-      let device_name: Vec<u8> = get_device_info(device, CL_DEVICE_NAME)?;
-      let device_vendor: Vec<u8> = get_device_info(device, CL_DEVICE_VENDOR)?;
-      let driver_version: Vec<u8> = get_device_info(device, CL_DRIVER_VERSION)?;
-      let device_version: Vec<u8> = get_device_info(device, CL_DEVICE_VERSION)?;
-      let max_compute_units: Vec<u8> = get_device_info(device, CL_DEVICE_MAX_COMPUTE_UNITS)?;
-      let max_clock_frequency: Vec<u8> = get_device_info(device, CL_DEVICE_MAX_CLOCK_FREQUENCY)?;
-      let max_mem_alloc_size: Vec<u8> = get_device_info(device, CL_DEVICE_MAX_MEM_ALLOC_SIZE)?;
-      let global_mem_size: Vec<u8> = get_device_info(device, CL_DEVICE_GLOBAL_MEM_SIZE)?;
-
-      // Cast device info to proper types
-      let device_name: String = bytes_into_string(device_name); // TODO: Limit to 255 chars?
-      let device_vendor: String = bytes_into_string(device_vendor); // TODO: Limit to 255 chars?
-      let driver_version: String = bytes_into_string(driver_version); // TODO: Limit to 64 chars?
-      let device_version: String = bytes_into_string(device_version); // TODO: Limit to 64 chars?
-      let max_compute_units: cl_uint = bytes_into(max_compute_units)?;
-      let max_clock_frequency: cl_uint = bytes_into(max_clock_frequency)?;
-      let max_mem_alloc_size: cl_ulong = bytes_into(max_mem_alloc_size)?;
-      let global_mem_size: cl_ulong = bytes_into(global_mem_size)?;
-
-      // Set default driver/device versions (unknown)
-      let driver_version: String = if driver_version.is_empty() {
-        String::from("?")
-      } else {
-        driver_version
-      };
-
-      let device_version: String = if device_version.is_empty() {
-        String::from("?")
-      } else {
-        device_version
-      };
-
-      // Calculate memory allocation
-      let mut memory_size_mb: cl_ulong = 0;
-
-      if !config.memsizes.is_empty() {
-        if config.memsizes.len() == 1 {
-          memory_size_mb = config.memsizes[0].into();
-        } else if index < config.memsizes.len() {
-          memory_size_mb = config.memsizes[index].into();
-        }
-      }
-
-      if memory_size_mb == 0 {
-        let memory_size_gb: cl_ulong = if is_amd {
-          max_mem_alloc_size
-        } else {
-          global_mem_size / 2
-        };
-
-        memory_size_mb = ((memory_size_gb as f64 / ONE_GB) * 1024.0) as cl_ulong;
-      }
-
-      let nonces_per_run: cl_ulong = (memory_size_mb * ONE_MB) / (ARGON2_BLOCK_SIZE * ARGON2_MEMORY_COST);
-      let jobs_per_block: cl_uint = if is_amd { 2 } else { 1 };
-      let shmem_size: size_t = THREADS_PER_LANE * 2 * size_of::<cl_uint>() * jobs_per_block as size_t;
-      let blocks_mem_size: cl_ulong =
-        (ARGON2_MEMORY_COST + if is_amd { 1 } else { 0 }) * ARGON2_BLOCK_SIZE * nonces_per_run;
-      let blocks_mem_size: size_t = blocks_mem_size as size_t;
-
-      println!("[+] Device #{}", gindex);
-      println!("[+] - Name           = {}", device_name);
-      println!("[+] - Vendor         = {}", device_vendor);
-      println!("[+] - Driver         = {}", driver_version);
-      println!("[+] - OpenCL         = {}", device_version);
-      println!("[+] - Jobs Per Block = {}", jobs_per_block);
-      println!(
-        "[+] - {} Compute Units @ {} MHz",
-        max_compute_units, max_clock_frequency
-      );
-      println!(
-        "[+] - Using {} MB of Global Memory, Nonces Per Run: {}",
-        memory_size_mb, nonces_per_run
-      );
-
-      let mut worker: Worker = Worker {
-        device_name,
-        device_vendor,
-        driver_version,
-        device_version,
-        max_compute_units,
-        max_clock_frequency,
-        max_mem_alloc_size,
-        global_mem_size,
-        device_index: gindex,
-        device_id: device,
-        nonces_per_run: nonces_per_run as cl_uint,
-        init_memory_global_size: [nonces_per_run as size_t, jobs_per_block as size_t],
-        init_memory_local_size: [NONCES_PER_GROUP, jobs_per_block as size_t],
-        argon2_global_size: [THREADS_PER_LANE, nonces_per_run as size_t],
-        argon2_local_size: [THREADS_PER_LANE, jobs_per_block as size_t],
-        find_nonce_global_size: [nonces_per_run as size_t],
-        find_nonce_local_size: [NONCES_PER_GROUP],
-        ..Worker::new()
-      };
-
-      println!("[+] Creating OpenCL Context");
-
-      worker.context = create_context(device)?;
-
-      println!("[+] Creating OpenCL Buffers");
-
-      worker.mem_argon2_blocks = create_buffer(worker.context, blocks_mem_size)?;
-      worker.mem_initial_seed = create_buffer(worker.context, INITIAL_SEED_SIZE)?;
-      worker.mem_nonce = create_buffer(worker.context, size_of::<cl_uint>())?;
-
-      println!("[+] Creating OpenCL Program");
-
-      // Cast kernel/program args to pointers
-      let mem_initial_seed_ptr: *const c_void = &worker.mem_initial_seed as *const *mut c_void as *const c_void;
-      let memory_cost_ptr: *const c_void = &_ARGON2_MEMORY_COST as *const cl_uint as *const c_void;
-      let mem_argon2_blocks_ptr: *const c_void = &worker.mem_argon2_blocks as *const *mut c_void as *const c_void;
-      let mem_nonce_ptr: *const c_void = &worker.mem_nonce as *const *mut c_void as *const c_void;
-      let program_sources: *const *const c_char = SOURCES.as_ptr() as *const *const c_char;
-
-      worker.program = create_program_with_source(worker.context, program_sources)?;
-
-      println!("[+] Building OpenCL program");
-
-      let _: () = build_program(worker.program, device, if is_amd { "-Werror -DAMD" } else { "-Werror" })?;
-
-      println!("[+] Creating OpenCL Command Queue");
-
-      worker.queue = create_command_queue(worker.context, device)?;
-
-      println!("[+] Creating OpenCL Kernel (init)");
-
-      worker.kernel_init_memory = {
-        let kernel = create_kernel(worker.program, "init_memory\0" as *const str as *const c_char)?;
-        let _: () = set_kernel_arg(kernel, 0, size_of::<cl_mem>(), mem_initial_seed_ptr)?;
-        let _: () = set_kernel_arg(kernel, 1, size_of::<cl_mem>(), mem_argon2_blocks_ptr)?;
-        let _: () = set_kernel_arg(kernel, 2, size_of::<cl_uint>(), memory_cost_ptr)?;
-        kernel
-      };
-
-      println!("[+] Creating OpenCL Kernel (argon2)");
-
-      worker.kernel_argon2 = {
-        let kernel = create_kernel(worker.program, "argon2\0" as *const str as *const c_char)?;
-        let _: () = set_kernel_arg(kernel, 0, shmem_size, null())?;
-        let _: () = set_kernel_arg(kernel, 1, size_of::<cl_mem>(), mem_argon2_blocks_ptr)?;
-        let _: () = set_kernel_arg(kernel, 2, size_of::<cl_uint>(), memory_cost_ptr)?;
-        kernel
-      };
-
-      println!("[+] Creating OpenCL Kernel (nonce)");
-
-      worker.kernel_find_nonce = {
-        let kernel = create_kernel(worker.program, "find_nonce\0" as *const str as *const c_char)?;
-        // arg 0 is not available yet
-        let _: () = set_kernel_arg(kernel, 1, size_of::<cl_mem>(), mem_argon2_blocks_ptr)?;
-        let _: () = set_kernel_arg(kernel, 2, size_of::<cl_uint>(), memory_cost_ptr)?;
-        let _: () = set_kernel_arg(kernel, 3, size_of::<cl_mem>(), mem_nonce_ptr)?;
-        kernel
-      };
-
-      workers.push(worker);
-    }
+    workers.push(create_worker(device, tuning)?);
   }
 
   if workers.is_empty() {
@@ -515,3 +471,163 @@ unsafe fn initialize(config: &MinerConfig) -> Result<Vec<Worker>, Error> {
 
   Ok(workers)
 }
+
+// Releases a worker that is not used anymore, failures only leak its memory.
+fn release_worker(mut worker: Worker) {
+  if let Err(error) = unsafe { worker.release() } {
+    eprintln!("[-] Failed to release Device #{}: {:?}", worker.device_index, error);
+  }
+}
+
+/// Returns the nonces per run of `device` with `tuning`, and the size in bytes
+/// of the Argon2 blocks buffer they need.
+///
+/// A `global_size` sets the nonces per run, otherwise they are derived from
+/// the `memory` size, by default the largest allocation the device supports.
+pub fn blocks_memory(device: &Device, tuning: &Tuning) -> (cl_ulong, cl_ulong) {
+  let is_amd: bool = device.is_amd();
+
+  // Calculate memory allocation
+  let mut memory_size_mb: cl_ulong = tuning.memory.unwrap_or(0).into();
+
+  if memory_size_mb == 0 {
+    let memory_size_gb: cl_ulong = if is_amd {
+      device.max_mem_alloc_size
+    } else {
+      device.global_mem_size / 2
+    };
+
+    memory_size_mb = ((memory_size_gb as f64 / ONE_GB) * 1024.0) as cl_ulong;
+  }
+
+  let nonces_per_run: cl_ulong = match tuning.global_size {
+    Some(global_size) => global_size.into(),
+    None => (memory_size_mb * ONE_MB) / (ARGON2_BLOCK_SIZE * ARGON2_MEMORY_COST),
+  };
+
+  let blocks_mem_size: cl_ulong =
+    (ARGON2_MEMORY_COST + if is_amd { 1 } else { 0 }) * ARGON2_BLOCK_SIZE * nonces_per_run;
+
+  (nonces_per_run, blocks_mem_size)
+}
+
+unsafe fn create_worker(device: &Device, tuning: Tuning) -> Result<Worker, Error> {
+  let is_amd: bool = device.is_amd();
+
+  let (nonces_per_run, blocks_mem_size) = blocks_memory(device, &tuning);
+
+  let jobs_per_block: cl_uint = tuning.jobs_per_block.unwrap_or(if is_amd { 2 } else { 1 });
+  let nonces_per_group: size_t = tuning.local_size.unwrap_or(NONCES_PER_GROUP);
+
+  let shmem_size: size_t = THREADS_PER_LANE * 2 * size_of::<cl_uint>() * jobs_per_block as size_t;
+  let blocks_mem_size: size_t = blocks_mem_size as size_t;
+
+  // Build options, extended by the defines of the device profile
+  let mut options: String = String::from(if is_amd { "-Werror -DAMD" } else { "-Werror" });
+
+  for define in tuning.defines.iter() {
+    options.push_str(" -D");
+    options.push_str(define);
+  }
+
+  println!("[+] Device #{}", device.index);
+  println!("[+] - Name           = {}", device.device_name);
+  println!("[+] - Vendor         = {}", device.device_vendor);
+  println!("[+] - Driver         = {}", device.driver_version);
+  println!("[+] - OpenCL         = {}", device.device_version);
+  println!("[+] - Jobs Per Block = {}", jobs_per_block);
+  println!("[+] - Nonces / Group = {}", nonces_per_group);
+  println!(
+    "[+] - {} Compute Units @ {} MHz",
+    device.max_compute_units, device.max_clock_frequency
+  );
+  println!(
+    "[+] - Using {} MB of Global Memory, Nonces Per Run: {}",
+    blocks_mem_size as cl_ulong / ONE_MB,
+    nonces_per_run
+  );
+
+  let mut worker: Worker = Worker {
+    device_name: device.device_name.clone(),
+    device_vendor: device.device_vendor.clone(),
+    driver_version: device.driver_version.clone(),
+    device_version: device.device_version.clone(),
+    max_compute_units: device.max_compute_units,
+    max_clock_frequency: device.max_clock_frequency,
+    max_mem_alloc_size: device.max_mem_alloc_size,
+    global_mem_size: device.global_mem_size,
+    device_index: device.index,
+    device_id: device.device_id,
+    nonces_per_run: nonces_per_run as cl_uint,
+    init_memory_global_size: [nonces_per_run as size_t, jobs_per_block as size_t],
+    init_memory_local_size: [nonces_per_group, jobs_per_block as size_t],
+    argon2_global_size: [THREADS_PER_LANE, nonces_per_run as size_t],
+    argon2_local_size: [THREADS_PER_LANE, jobs_per_block as size_t],
+    find_nonce_global_size: [nonces_per_run as size_t],
+    find_nonce_local_size: [nonces_per_group],
+    tuning,
+    ..Worker::new()
+  };
+
+  println!("[+] Creating OpenCL Context");
+
+  worker.context = create_context(device.device_id)?;
+
+  println!("[+] Creating OpenCL Buffers");
+
+  worker.mem_argon2_blocks = create_buffer(worker.context, blocks_mem_size)?;
+  worker.mem_initial_seed = create_buffer(worker.context, INITIAL_SEED_SIZE)?;
+  worker.mem_nonce = create_buffer(worker.context, size_of::<cl_uint>())?;
+
+  println!("[+] Creating OpenCL Program");
+
+  // Cast kernel/program args to pointers
+  let mem_initial_seed_ptr: *const c_void = &worker.mem_initial_seed as *const *mut c_void as *const c_void;
+  let memory_cost_ptr: *const c_void = &_ARGON2_MEMORY_COST as *const cl_uint as *const c_void;
+  let mem_argon2_blocks_ptr: *const c_void = &worker.mem_argon2_blocks as *const *mut c_void as *const c_void;
+  let mem_nonce_ptr: *const c_void = &worker.mem_nonce as *const *mut c_void as *const c_void;
+  let program_sources: *const *const c_char = SOURCES.as_ptr() as *const *const c_char;
+
+  worker.program = create_program_with_source(worker.context, program_sources)?;
+
+  println!("[+] Building OpenCL program");
+
+  let _: () = build_program(worker.program, device.device_id, &options)?;
+
+  println!("[+] Creating OpenCL Command Queue");
+
+  worker.queue = create_command_queue(worker.context, device.device_id)?;
+
+  println!("[+] Creating OpenCL Kernel (init)");
+
+  worker.kernel_init_memory = {
+    let kernel = create_kernel(worker.program, "init_memory\0" as *const str as *const c_char)?;
+    let _: () = set_kernel_arg(kernel, 0, size_of::<cl_mem>(), mem_initial_seed_ptr)?;
+    let _: () = set_kernel_arg(kernel, 1, size_of::<cl_mem>(), mem_argon2_blocks_ptr)?;
+    let _: () = set_kernel_arg(kernel, 2, size_of::<cl_uint>(), memory_cost_ptr)?;
+    kernel
+  };
+
+  println!("[+] Creating OpenCL Kernel (argon2)");
+
+  worker.kernel_argon2 = {
+    let kernel = create_kernel(worker.program, "argon2\0" as *const str as *const c_char)?;
+    let _: () = set_kernel_arg(kernel, 0, shmem_size, null())?;
+    let _: () = set_kernel_arg(kernel, 1, size_of::<cl_mem>(), mem_argon2_blocks_ptr)?;
+    let _: () = set_kernel_arg(kernel, 2, size_of::<cl_uint>(), memory_cost_ptr)?;
+    kernel
+  };
+
+  println!("[+] Creating OpenCL Kernel (nonce)");
+
+  worker.kernel_find_nonce = {
+    let kernel = create_kernel(worker.program, "find_nonce\0" as *const str as *const c_char)?;
+    // arg 0 is not available yet
+    let _: () = set_kernel_arg(kernel, 1, size_of::<cl_mem>(), mem_argon2_blocks_ptr)?;
+    let _: () = set_kernel_arg(kernel, 2, size_of::<cl_uint>(), memory_cost_ptr)?;
+    let _: () = set_kernel_arg(kernel, 3, size_of::<cl_mem>(), mem_nonce_ptr)?;
+    kernel
+  };
+
+  Ok(worker)
+}
@@ -1,7 +1,13 @@
 mod config;
+mod device;
+mod meter;
 mod miner;
+mod profile;
 mod worker;
 
 pub use self::config::*;
+pub use self::device::*;
+pub use self::meter::*;
 pub use self::miner::*;
+pub use self::profile::*;
 pub use self::worker::*;
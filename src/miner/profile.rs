@@ -0,0 +1,156 @@
+use regex::escape;
+use regex::Regex;
+use serde::de::Error as _;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+
+use crate::miner::Device;
+
+/// A device name/vendor/driver pattern.
+///
+/// Patterns wrapped in slashes (`/RX 5[78]0/`) are regular expressions,
+/// anything else is a case-insensitive glob (`*GTX 1080*`).
+#[derive(Clone, Debug)]
+pub struct Pattern {
+  source: String,
+  regex: Regex,
+}
+
+impl Pattern {
+  pub fn new(source: &str) -> Result<Self, String> {
+    let expression: String = if source.len() > 1 && source.starts_with('/') && source.ends_with('/') {
+      source[1..source.len() - 1].to_string()
+    } else {
+      glob_to_regex(source)
+    };
+
+    Regex::new(&expression)
+      .map(|regex| Self {
+        source: source.into(),
+        regex,
+      })
+      .map_err(|error| format!("invalid pattern `{}`: {}", source, error))
+  }
+
+  #[inline]
+  pub fn is_match(&self, value: &str) -> bool {
+    self.regex.is_match(value)
+  }
+}
+
+impl PartialEq for Pattern {
+  #[inline]
+  fn eq(&self, other: &Self) -> bool {
+    self.source == other.source
+  }
+}
+
+impl Serialize for Pattern {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&self.source)
+  }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let source: String = String::deserialize(deserializer)?;
+
+    Self::new(&source).map_err(D::Error::custom)
+  }
+}
+
+fn glob_to_regex(glob: &str) -> String {
+  let mut expression: String = String::from("(?i)^");
+
+  for character in glob.chars() {
+    match character {
+      '*' => expression.push_str(".*"),
+      '?' => expression.push('.'),
+      character => expression.push_str(&escape(&character.to_string())),
+    }
+  }
+
+  expression.push('$');
+  expression
+}
+
+/// Patterns a device has to match. Every given pattern has to match.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeviceMatch {
+  /// Matched against `CL_DEVICE_NAME`
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub name: Option<Pattern>,
+  /// Matched against `CL_DEVICE_VENDOR`
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub vendor: Option<Pattern>,
+  /// Matched against `CL_DRIVER_VERSION`
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub driver: Option<Pattern>,
+}
+
+impl DeviceMatch {
+  pub fn is_match(&self, device: &Device) -> bool {
+    let matches =
+      |pattern: &Option<Pattern>, value: &str| pattern.as_ref().map_or(true, |pattern| pattern.is_match(value));
+
+    matches(&self.name, &device.device_name)
+      && matches(&self.vendor, &device.device_vendor)
+      && matches(&self.driver, &device.driver_version)
+  }
+}
+
+/// Kernel settings of a single device. Unset values use the vendor defaults.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Tuning {
+  /// Allocated memory in Mb
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub memory: Option<u32>,
+  /// Argon2d jobs per work group
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub jobs_per_block: Option<u32>,
+  /// Nonces per work group (local size of the init/nonce kernels)
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub local_size: Option<usize>,
+  /// Nonces per run (global size), overrides the size derived from `memory`
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub global_size: Option<u32>,
+  /// Extra preprocessor defines passed to the OpenCL compiler, eg. `FOO=1`
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub defines: Vec<String>,
+}
+
+impl Tuning {
+  /// Overrides the values of `self` with the ones set in `other`.
+  pub fn merge(&mut self, other: &Self) {
+    if other.memory.is_some() {
+      self.memory = other.memory;
+    }
+
+    if other.jobs_per_block.is_some() {
+      self.jobs_per_block = other.jobs_per_block;
+    }
+
+    if other.local_size.is_some() {
+      self.local_size = other.local_size;
+    }
+
+    if other.global_size.is_some() {
+      self.global_size = other.global_size;
+    }
+
+    self.defines.extend(other.defines.iter().cloned());
+  }
+}
+
+/// Tuning applied to every device matching a set of patterns.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeviceProfile {
+  #[serde(rename = "match")]
+  pub matcher: DeviceMatch,
+  pub tuning: Tuning,
+}
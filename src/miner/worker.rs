@@ -21,6 +21,7 @@ use crate::ffi::size_t;
 use crate::ffi::CL_FALSE;
 use crate::ffi::CL_SUCCESS;
 use crate::ffi::CL_TRUE;
+use crate::miner::Tuning;
 use crate::opencl::enqueue_write_buffer;
 use crate::opencl::release_command_queue;
 use crate::opencl::release_context;
@@ -45,6 +46,8 @@ pub struct Worker {
   pub nonces_per_run: cl_uint,
   pub device_index: cl_uint,
   pub device_id: cl_device_id,
+  /// Tuning the worker was built with
+  pub tuning: Tuning,
   pub context: cl_context,
   pub queue: cl_command_queue,
   pub program: cl_program,
@@ -82,6 +85,7 @@ impl Worker {
       nonces_per_run: 0,
       device_index: 0,
       device_id: null_mut(),
+      tuning: Tuning::default(),
       context: null_mut(),
       queue: null_mut(),
       program: null_mut(),
@@ -132,9 +132,9 @@ pub unsafe fn create_program_with_source(
 }
 
 #[inline]
-pub unsafe fn build_program(program: cl_program, device: cl_device_id, options: &'static str) -> Result<(), Error> {
-  let options_ptr: *const c_char = CString::new(options).unwrap().as_ptr();
-  let result: cl_int = clBuildProgram(program, 0, null(), options_ptr, None, null_mut());
+pub unsafe fn build_program(program: cl_program, device: cl_device_id, options: &str) -> Result<(), Error> {
+  let options: CString = CString::new(options).map_err(|_| Error::OpenCL(CL_INVALID_VALUE, "clBuildProgram"))?;
+  let result: cl_int = clBuildProgram(program, 0, null(), options.as_ptr(), None, null_mut());
 
   if result != CL_SUCCESS {
     let info: Vec<u8> = get_program_build_info(program, device)?;
@@ -2,8 +2,14 @@ use nimiq_block::Block;
 use nimiq_block::BlockHeader;
 use nimiq_block::BlockInterlink;
 use nimiq_block::Target;
+use nimiq_blockchain::Blockchain;
+use nimiq_blockchain::PushResult;
 use nimiq_hash::Blake2bHash;
 use nimiq_hash::Hash;
+use std::sync::Arc;
+use std::thread::spawn;
+
+const TAG: &'static str = "PoolChain";
 
 #[derive(Debug)]
 pub struct PoolChain {
@@ -44,3 +50,21 @@ impl PoolChain {
     }
   }
 }
+
+/// Pushes the full `block` into the local blockchain, the consensus relays it
+/// to the peers once accepted.
+///
+/// The push runs on its own thread: blockchain listeners take the miner locks,
+/// the mining thread must not wait for them.
+pub fn publish_block(blockchain: Arc<Blockchain<'static>>, block: Block) {
+  spawn(move || {
+    let hash: Blake2bHash = block.header.hash();
+
+    match blockchain.push(block) {
+      PushResult::Extended | PushResult::Rebranched => {
+        println!("[{}] Block {} accepted, relaying to peers", TAG, hash);
+      }
+      result => eprintln!("[{}] Block {} not accepted: {:?}", TAG, hash, result),
+    }
+  });
+}
@@ -1,86 +1,230 @@
 use base64::encode;
+use futures::future::Either;
 use futures::sync::mpsc::unbounded;
 use futures::sync::mpsc::UnboundedReceiver;
 use futures::sync::mpsc::UnboundedSender;
+use futures::sync::oneshot;
 use futures::Async;
+use futures::AsyncSink;
 use futures::Future;
 use futures::Poll;
 use futures::Sink;
+use futures::StartSend;
 use futures::Stream;
+use nimiq_hash::Blake2bHash;
 use nimiq_keys::Address;
-use serde_json::from_str;
+use nimiq_utils::observer::Notifier;
+use rand::thread_rng;
+use rand::Rng;
 use serde_json::to_string;
 use std::sync::Arc;
+use std::sync::Mutex;
 use std::sync::RwLock;
+use std::thread::sleep;
 use std::thread::spawn;
+use std::time::Duration;
 use tokio::runtime::current_thread;
-use tokio_tungstenite::connect_async;
 use tungstenite::Message;
 use url::Url;
 
 use crate::error::Error;
+use crate::pool::open_socket;
+use crate::pool::DeviceData;
 use crate::pool::PoolConfig;
+use crate::pool::PoolEndpoint;
+use crate::pool::PoolEvent;
 use crate::pool::PoolMessage;
-use crate::pool::PoolMode;
+use crate::pool::PoolProxy;
 use crate::pool::PoolState;
+use crate::pool::TlsConfig;
 
 type ArcState = Arc<RwLock<PoolState<PoolMessage>>>;
 type Receiver = UnboundedReceiver<PoolMessage>;
 type Sender = UnboundedSender<PoolMessage>;
+type ArcConnection = Arc<Mutex<Connection>>;
+type ArcNotifier = Arc<RwLock<Notifier<'static, PoolEvent>>>;
+type Outgoing = UnboundedSender<Message>;
 
-#[derive(Debug)]
+// Delay before the first reconnect, doubled after every failed attempt
+const BACKOFF_MIN: u64 = 1_000;
+const BACKOFF_MAX: u64 = 60_000;
+
+#[derive(Debug, Default)]
+struct Connection {
+  // Bumped by `connect`, stops the supervisor of the previous connection
+  id: usize,
+  // Dropping this closes the socket
+  close: Option<oneshot::Sender<()>>,
+  // Messages written to the socket after registering, set while it is open
+  outgoing: Option<Outgoing>,
+  // Whether the current attempt completed the handshake
+  established: bool,
+}
+
+/// A websocket connection to the pool.
+///
+/// Incoming messages are read as a `Stream`, outgoing ones are written with
+/// `send` or as a `Sink`, in order, after the `register` message.
+///
+/// Lost connections are reopened with an exponential backoff, and registered
+/// again. Listeners of `notifier` observe the connection state.
+#[derive(Clone)]
 pub struct PoolClient {
   state: ArcState,
   sender: Sender,
+  connection: ArcConnection,
+  // Sent with every register message
+  data: Arc<Mutex<DeviceData>>,
+  pub notifier: ArcNotifier,
 }
 
 impl PoolClient {
-  pub fn new(config: &PoolConfig) -> Result<Self, Error> {
+  /// Creates the client and connects to the pool at `endpoint`.
+  pub fn new(config: &PoolConfig, endpoint: &PoolEndpoint, data: DeviceData) -> Result<Self, Error> {
     let state = ArcState::default();
 
     let (sender, receiver) = unbounded();
 
-    // use hash::Blake2bHash;
-    // use hash::Hash;
+    let read = Self::init_receiver(receiver, Arc::clone(&state));
 
-    // let info = get_network_info(NetworkId::Main).unwrap();
-    // let hash: Blake2bHash = info.genesis_block.header.hash();
+    spawn(move || current_thread::block_on_all(read));
 
-    // TODO: FIXME
-    let hash = [
-      38, 74, 175, 138, 79, 152, 40, 167, 108, 85, 6, 53, 218, 7, 142, 180, 102, 48, 106, 24, 159, 204, 3, 113, 11,
-      238, 159, 100, 156, 134, 157, 18,
-    ];
+    let client = Self {
+      sender,
+      state,
+      connection: ArcConnection::default(),
+      data: Arc::new(Mutex::new(data)),
+      notifier: Arc::new(RwLock::new(Notifier::new())),
+    };
 
-    // TODO: Device Data
-    //   startDifficulty
-    //   deviceName
-    //   minerVersion
+    client.connect(config, endpoint)?;
 
-    let address = Address::from_user_friendly_address(config.address)
-      .unwrap()
-      .to_user_friendly_address();
+    Ok(client)
+  }
 
-    // TODO: FIXME
-    let device = 12345678;
+  /// Connects and registers to the pool at `endpoint`, closing the previous
+  /// connection. Messages of both connections end up in the same stream.
+  pub fn connect(&self, config: &PoolConfig, endpoint: &PoolEndpoint) -> Result<(), Error> {
+    let data: DeviceData = self.data.lock().unwrap().clone();
+    let register = Message::Text(to_string(&Self::register(config, data)?)?);
+    let url: Url = endpoint.wsurl(config.scheme)?;
+    let tls: TlsConfig = TlsConfig::from(config);
+    let proxy: Option<PoolProxy> = config.proxy.clone();
 
-    let message = PoolMessage::Register {
-      address,
-      device,
-      data: None,
-      mode: PoolMode::Nano,
-      genesis: encode(&hash),
+    let id: usize = {
+      let mut connection = self.connection.lock().unwrap();
+
+      // Dropping the handles closes the previous socket
+      connection.id += 1;
+      connection.close = None;
+      connection.outgoing = None;
+      connection.id
     };
 
-    let register = Message::Text(to_string(&message)?);
+    let client: Self = self.clone();
 
-    let read = Self::init_receiver(receiver, Arc::clone(&state));
-    let send = Self::init_sender(sender.clone(), Arc::clone(&state), register, config.wsurl());
+    spawn(move || client.supervise(id, register, url, tls, proxy));
 
-    spawn(move || current_thread::block_on_all(read));
-    spawn(move || current_thread::block_on_all(send));
+    Ok(())
+  }
+
+  // Keeps connection `id` open until `connect` is called again.
+  fn supervise(&self, id: usize, register: Message, url: Url, tls: TlsConfig, proxy: Option<PoolProxy>) {
+    let mut failures: u32 = 0;
+
+    loop {
+      let (close, closed) = oneshot::channel();
+
+      {
+        let mut connection = self.connection.lock().unwrap();
+
+        if connection.id != id {
+          return;
+        }
+
+        connection.close = Some(close);
+        connection.established = false;
+      }
+
+      self.notify(PoolEvent::Connecting(url.clone()));
+
+      let result = current_thread::block_on_all(
+        self
+          .init_sender(id, register.clone(), url.clone(), tls.clone(), proxy.clone())
+          .select2(closed),
+      );
+
+      let established: bool = {
+        let mut connection = self.connection.lock().unwrap();
+
+        // Closed by `connect`
+        if connection.id != id {
+          return;
+        }
+
+        connection.close = None;
+        connection.outgoing = None;
+        connection.established
+      };
+
+      let reason: String = match result {
+        Ok(Either::A(_)) => "Connection closed by the pool".into(),
+        Err(Either::A((error, _))) => format!("{:?}", error),
+        Ok(Either::B(_)) | Err(Either::B(_)) => return,
+      };
+
+      eprintln!("[x] WebSocket Error: {}", reason);
+
+      failures = if established { 1 } else { failures + 1 };
+
+      let delay: Duration = backoff(failures);
+
+      self.notify(PoolEvent::Disconnected(reason));
+      self.notify(PoolEvent::Reconnecting(delay));
+
+      sleep(delay);
+    }
+  }
+
+  #[inline]
+  fn notify(&self, event: PoolEvent) {
+    self.notifier.read().unwrap().notify(event);
+  }
 
-    Ok(Self { sender, state })
+  /// Replaces the device data sent on the next `connect`.
+  #[inline]
+  pub fn set_device_data(&self, data: DeviceData) {
+    *self.data.lock().unwrap() = data;
+  }
+
+  /// Writes `message` to the pool after the ones sent before.
+  ///
+  /// Fails with `Error::Disconnected` while the socket is not open.
+  pub fn send(&self, message: &PoolMessage) -> Result<(), Error> {
+    let message: Message = Message::Text(to_string(message)?);
+
+    match self.connection.lock().unwrap().outgoing.as_ref() {
+      Some(outgoing) => outgoing.unbounded_send(message).map_err(|_| Error::Disconnected),
+      None => Err(Error::Disconnected),
+    }
+  }
+
+  fn register(config: &PoolConfig, data: DeviceData) -> Result<PoolMessage, Error> {
+    let hash: Blake2bHash = config.network.genesis_hash();
+
+    let address = Address::from_user_friendly_address(&config.address)
+      .map_err(|error| Error::Config("address".into(), format!("{:?}", error)))?
+      .to_user_friendly_address();
+
+    let device: u32 = config.device_id()?;
+
+    Ok(PoolMessage::Register {
+      address,
+      device,
+      data: Some(data),
+      mode: config.mode,
+      genesis: encode(hash.as_bytes()),
+    })
   }
 
   fn init_receiver(receiver: Receiver, state: ArcState) -> impl Future<Item = (), Error = ()> {
@@ -91,50 +235,128 @@ impl PoolClient {
   }
 
   fn init_sender(
-    sender: Sender,
-    state: ArcState,
+    &self,
+    id: usize,
     message: Message,
     url: Url,
-  ) -> impl Future<Item = ((), ()), Error = Error> {
-    let dispatch_message = move |message: Result<PoolMessage, Error>| {
-      if let Ok(message) = message {
-        sender.unbounded_send(message)?;
-      } else {
-        eprintln!("[x] WebSocket Message Error: {:?}", message);
-      }
+    tls: TlsConfig,
+    proxy: Option<PoolProxy>,
+  ) -> impl Future<Item = (), Error = Error> {
+    let sender: Sender = self.sender.clone();
+    let client: Self = self.clone();
+
+    let dispatch_message = move |message: PoolMessage| {
+      sender.unbounded_send(message)?;
 
       Ok(())
     };
 
-    let parse_message = |message: Message| -> Result<PoolMessage, Error> {
+    // Invalid messages are logged and skipped, they don't close the connection
+    let parse_message = |message: Message| -> Option<PoolMessage> {
       match message {
-        Message::Text(text) => {
-          // println!("[+] JSON Text: {:?}", text);
-
-          from_str(&text).map_err(Into::into)
+        Message::Text(text) => match PoolMessage::parse(&text) {
+          Ok(message) => Some(message),
+          Err(error) => {
+            eprintln!("[x] WebSocket Message Error: {:?} - {}", error, text);
+            None
+          }
+        },
+        Message::Binary(data) => {
+          eprintln!("[x] WebSocket Binary Message ignored ({} bytes)", data.len());
+          None
+        }
+        // Pings are answered by tungstenite
+        Message::Ping(_) | Message::Pong(_) => None,
+        // The stream ends once tungstenite completed the close handshake
+        Message::Close(Some(frame)) => {
+          eprintln!("[x] WebSocket Closed: {} {}", u16::from(frame.code), frame.reason);
+          None
+        }
+        Message::Close(None) => {
+          eprintln!("[x] WebSocket Closed");
+          None
         }
-        _ => unreachable!(),
       }
     };
 
-    connect_async(url)
-      .map_err(Error::Connect)
-      .map(|(duplex, _)| duplex.split())
+    open_socket(url.clone(), tls, proxy)
+      .map(|duplex| duplex.split())
       .and_then(move |(sink, stream)| {
         println!("[+] WebSocket Handshake Successfully Completed");
 
-        state.write().unwrap().activate();
+        client.state.write().unwrap().activate();
+
+        let (outgoing, receiver) = unbounded();
+
+        {
+          let mut connection = client.connection.lock().unwrap();
+
+          if connection.id == id {
+            connection.outgoing = Some(outgoing);
+            connection.established = true;
+          }
+        }
+
+        client.notify(PoolEvent::Connected(url));
 
         stream
-          .filter(|event| event.is_text())
           .map_err(Error::Read)
-          .map(parse_message)
+          .filter_map(parse_message)
           .for_each(dispatch_message)
-          .join(sink.send(message).map_err(Error::Send).map(|_| ()))
+          // Either half ending closes the connection
+          .select(
+            sink
+              .send(message)
+              .map_err(Error::Send)
+              .and_then(move |sink| {
+                sink
+                  .sink_map_err(Error::Send)
+                  .send_all(receiver.map_err(|_| Error::Disconnected))
+              })
+              .map(|_| ()),
+          )
+          .map(|_| ())
+          .map_err(|(error, _)| error)
       })
   }
 }
 
+impl std::fmt::Debug for PoolClient {
+  fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    let connection = self.connection.lock().unwrap();
+
+    f.debug_struct("PoolClient")
+      .field("connection", &connection.id)
+      .field("established", &connection.established)
+      .finish()
+  }
+}
+
+/// Returns the delay before the next connection attempt after `failures`
+/// failed ones, with jitter so rigs don't reconnect in lockstep.
+fn backoff(failures: u32) -> Duration {
+  let delay: u64 = BACKOFF_MIN
+    .saturating_mul(1 << failures.saturating_sub(1).min(16))
+    .min(BACKOFF_MAX);
+
+  Duration::from_millis(delay / 2 + thread_rng().gen_range(0, delay / 2 + 1))
+}
+
+impl Sink for PoolClient {
+  type SinkItem = PoolMessage;
+  type SinkError = Error;
+
+  fn start_send(&mut self, item: Self::SinkItem) -> StartSend<Self::SinkItem, Self::SinkError> {
+    PoolClient::send(self, &item)?;
+
+    Ok(AsyncSink::Ready)
+  }
+
+  fn poll_complete(&mut self) -> Poll<(), Self::SinkError> {
+    Ok(Async::Ready(()))
+  }
+}
+
 impl Stream for PoolClient {
   type Item = PoolMessage;
   type Error = Error;
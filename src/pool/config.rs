@@ -1,44 +1,591 @@
-use url::Url;
+use beserial::Deserialize;
+use hex::decode;
+use nimiq_keys::Address;
+use nimiq_keys::KeyPair;
+use nimiq_keys::PrivateKey;
+use serde::de::DeserializeOwned;
+use serde::Serializer;
+use serde_json::from_str;
+use serde_json::from_value;
+use serde_json::Map;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::env::vars;
+use std::fs::read_to_string;
+use std::path::Path;
+use std::str::FromStr;
 
-const DESIRED_SPS: u32 = 5;
+use crate::config::ONE_MB;
+use crate::error::Error;
+use crate::miner::blocks_memory;
+use crate::miner::Device;
+use crate::miner::DeviceProfile;
+use crate::miner::MinerConfig;
+use crate::miner::Tuning;
+use crate::pool::load_device_id;
+use crate::pool::normalize_pin;
+use crate::pool::NetworkName;
+use crate::pool::PoolEndpoint;
+use crate::pool::PoolMode;
+use crate::pool::PoolProxy;
+use crate::pool::PoolScheme;
+use crate::pool::Split;
+use crate::pool::TlsConfig;
+use crate::utils::strip_comments;
+
+/// Seconds between shares the start difficulty aims for.
+pub const DESIRED_SPS: u32 = 5;
+
+// Assumed until the hashrate was measured, in kH/s
+const DEFAULT_HASHRATE: u32 = 100;
+
+/// Prefix of the environment variables read by `PoolConfig::merge_env`.
+pub const ENV_PREFIX: &'static str = "PLUTONIUM_";
+
+/// Keys that can be set through `PoolConfig::set`.
+///
+/// `devices` and `memory` are also used for the `MinerConfig`.
+pub const CONFIG_KEYS: [&'static str; 23] = [
+  "network",
+  "address",
+  "payout_key",
+  "host",
+  "port",
+  "scheme",
+  "tls_ca",
+  "tls_pins",
+  "tls_insecure",
+  "proxy",
+  "pools",
+  "splits",
+  "mode",
+  "name",
+  "device_id",
+  "hashrate",
+  "devices",
+  "memory",
+  "profiles",
+  "overrides",
+  "db_path",
+  "db_size",
+  "db_max",
+];
 
 // Nimiq Acct: NQ33 G0T9 D63A TMN2 S18B 960Q S3Q3 TMYD KQJE
 // Nimiq Pin: 243915
 
-#[derive(Debug)]
+/// Configuration of the pool miner.
+///
+/// Values are merged from the following sources, later ones taking precedence:
+///
+/// 1. Defaults (`PoolConfig::default`)
+/// 2. The config file (`PoolConfig::load`)
+/// 3. `PLUTONIUM_*` environment variables (`PoolConfig::merge_env`)
+/// 4. Command-line flags
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub struct PoolConfig {
+  /// Nimiq network: main, test or dev
+  pub network: NetworkName,
   /// Wallet address
-  pub address: &'static str,
+  pub address: String,
+  /// Hex encoded private key of `address`, used to sign payout requests
+  #[serde(serialize_with = "redact")]
+  pub payout_key: Option<String>,
   /// Pool server
-  pub host: &'static str,
+  pub host: String,
   /// Pool port
   pub port: u16,
+  /// `ws` or `wss`, for pools without a scheme of their own
+  pub scheme: PoolScheme,
+  /// PEM file of additional CA certificates for `wss` pools
+  pub tls_ca: Option<String>,
+  /// SHA-256 fingerprints of the accepted pool certificates
+  pub tls_pins: Vec<String>,
+  /// Accepts any pool certificate, for testing only
+  pub tls_insecure: bool,
+  /// SOCKS5 or HTTP CONNECT proxy of the pool connections
+  pub proxy: Option<PoolProxy>,
+  /// Pools by priority, replaces `host` and `port` if set
+  pub pools: Vec<PoolEndpoint>,
+  /// Mining time split between addresses and pools, mined instead of `address`
+  pub splits: Vec<Split>,
+  /// Nano (pool blocks), smart (own blocks from the local node) or solo (no pool)
+  pub mode: PoolMode,
   /// Device name to show in the dashboard
-  pub name: &'static str,
-  /// Expected hashrate in kH/s
-  pub hashrate: u32,
+  pub name: String,
+  /// Pool device id, overrides the one stored in `db_path`
+  pub device_id: Option<u32>,
+  /// Expected hashrate in kH/s, overrides the measured one
+  pub hashrate: Option<u32>,
   // GPU devices to use
   pub devices: Vec<u32>,
-  // Allocated memory in Mb for each device
+  // Allocated memory in Mb for each device, overrides the profiles if set
   pub memory: Vec<u32>,
+  // Tuning profiles, applied to every matching device
+  pub profiles: Vec<DeviceProfile>,
+  // Tuning overrides by device index, applied on top of the profiles
+  pub overrides: BTreeMap<u32, Tuning>,
   //
   // Blockchain config
   //
-  pub db_path: &'static str,
+  /// Database directory, `./db/<network>` if unset
+  pub db_path: Option<String>,
   pub db_size: usize,
   pub db_max: u32,
 }
 
 impl PoolConfig {
-  #[inline]
-  pub fn difficulty(&self) -> u32 {
-    (1000 * self.hashrate * DESIRED_SPS) / (1 << 16)
+  /// Loads the config file at `path`.
+  ///
+  /// The file is JSON with optional `//` and `/* */` comments (see `miner.conf`).
+  /// Missing keys fall back to their default value, `validate` checks for the
+  /// required ones once every source is merged.
+  pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+    let (config, errors) = Self::load_partial(path)?;
+
+    if errors.is_empty() {
+      Ok(config)
+    } else {
+      Err(Error::Invalid(errors))
+    }
+  }
+
+  /// Loads the config file at `path` like `load`, but returns invalid and
+  /// unknown keys along with the config instead of failing. Invalid keys keep
+  /// their default value.
+  pub fn load_partial<P: AsRef<Path>>(path: P) -> Result<(Self, Vec<(String, String)>), Error> {
+    let path: &Path = path.as_ref();
+
+    let input: String = read_to_string(path).map_err(|error| Error::ConfigFile(path.to_path_buf(), error))?;
+
+    Self::parse_partial(&input)
+  }
+
+  /// Parses the contents of a config file.
+  pub fn parse(input: &str) -> Result<Self, Error> {
+    let (config, errors) = Self::parse_partial(input)?;
+
+    if errors.is_empty() {
+      Ok(config)
+    } else {
+      Err(Error::Invalid(errors))
+    }
   }
 
-  // TODO: Propagate error
+  /// Parses the contents of a config file, see `load_partial`.
+  pub fn parse_partial(input: &str) -> Result<(Self, Vec<(String, String)>), Error> {
+    let mut object: Map<String, Value> = match from_str::<Value>(&strip_comments(input))? {
+      Value::Object(object) => object,
+      _ => Err(Error::Config("<root>".into(), "expected a JSON object".into()))?,
+    };
+
+    let mut config: Self = Self::default();
+    let mut errors: Vec<(String, String)> = Vec::new();
+
+    if let Some(address) = optional(&mut object, "address", &mut errors) {
+      config.address = address;
+    }
+
+    if let Some(network) = optional(&mut object, "network", &mut errors) {
+      config.network = network;
+    }
+
+    if let Some(pools) = optional(&mut object, "pools", &mut errors) {
+      config.pools = pools;
+    }
+
+    if let Some(host) = optional(&mut object, "host", &mut errors) {
+      config.host = host;
+    }
+
+    if let Some(port) = port(&mut object, "port", &mut errors) {
+      config.port = port;
+    }
+
+    if let Some(scheme) = optional(&mut object, "scheme", &mut errors) {
+      config.scheme = scheme;
+    }
+
+    if let Some(tls_ca) = optional(&mut object, "tls_ca", &mut errors) {
+      config.tls_ca = Some(tls_ca);
+    }
+
+    if let Some(tls_pins) = optional(&mut object, "tls_pins", &mut errors) {
+      config.tls_pins = tls_pins;
+    }
+
+    if let Some(tls_insecure) = optional(&mut object, "tls_insecure", &mut errors) {
+      config.tls_insecure = tls_insecure;
+    }
+
+    if let Some(proxy) = optional(&mut object, "proxy", &mut errors) {
+      config.proxy = Some(proxy);
+    }
+
+    if let Some(payout_key) = optional(&mut object, "payout_key", &mut errors) {
+      config.payout_key = Some(payout_key);
+    }
+
+    if let Some(splits) = optional(&mut object, "splits", &mut errors) {
+      config.splits = splits;
+    }
+
+    if let Some(mode) = optional(&mut object, "mode", &mut errors) {
+      config.mode = mode;
+    }
+
+    if let Some(name) = optional(&mut object, "name", &mut errors) {
+      config.name = name;
+    }
+
+    if let Some(device_id) = optional(&mut object, "device_id", &mut errors) {
+      config.device_id = Some(device_id);
+    }
+
+    if let Some(hashrate) = optional(&mut object, "hashrate", &mut errors) {
+      config.hashrate = Some(hashrate);
+    }
+
+    if let Some(devices) = optional(&mut object, "devices", &mut errors) {
+      config.devices = devices;
+    }
+
+    if let Some(memory) = optional(&mut object, "memory", &mut errors) {
+      config.memory = memory;
+    }
+
+    if let Some(profiles) = optional(&mut object, "profiles", &mut errors) {
+      config.profiles = profiles;
+    }
+
+    if let Some(overrides) = optional(&mut object, "overrides", &mut errors) {
+      config.overrides = overrides;
+    }
+
+    if let Some(db_path) = optional(&mut object, "db_path", &mut errors) {
+      config.db_path = Some(db_path);
+    }
+
+    if let Some(db_size) = optional(&mut object, "db_size", &mut errors) {
+      config.db_size = db_size;
+    }
+
+    if let Some(db_max) = optional(&mut object, "db_max", &mut errors) {
+      config.db_max = db_max;
+    }
+
+    for key in object.keys() {
+      errors.push((key.clone(), "unknown key".into()));
+    }
+
+    Ok((config, errors))
+  }
+
+  /// Overrides values with the `PLUTONIUM_<KEY>` environment variables,
+  /// eg. `PLUTONIUM_ADDRESS` or `PLUTONIUM_DB_PATH`.
+  ///
+  /// Every invalid value is reported at once as `Error::Invalid`.
+  pub fn merge_env(&mut self) -> Result<(), Error> {
+    let mut errors: Vec<(String, String)> = Vec::new();
+
+    for (name, value) in vars() {
+      if !name.starts_with(ENV_PREFIX) {
+        continue;
+      }
+
+      let key: String = name[ENV_PREFIX.len()..].to_lowercase();
+
+      if !CONFIG_KEYS.contains(&key.as_str()) {
+        continue;
+      }
+
+      match self.set(&key, &value, &name) {
+        Ok(()) => {}
+        Err(Error::Config(key, message)) => errors.push((key, message)),
+        Err(error) => Err(error)?,
+      }
+    }
+
+    if errors.is_empty() {
+      Ok(())
+    } else {
+      Err(Error::Invalid(errors))
+    }
+  }
+
+  /// Sets the value of `key` from its string representation. Lists are
+  /// comma-separated, `splits`, `profiles` and `overrides` are JSON. `origin` names the
+  /// source of the value in errors.
+  pub fn set(&mut self, key: &str, value: &str, origin: &str) -> Result<(), Error> {
+    match key {
+      "network" => self.network = parse_value(value, origin)?,
+      "address" => self.address = value.into(),
+      "payout_key" => self.payout_key = Some(value.trim().into()),
+      "host" => self.host = value.into(),
+      "port" => self.port = parse_value(value, origin)?,
+      "scheme" => self.scheme = parse_value(value, origin)?,
+      "tls_ca" => self.tls_ca = Some(value.into()),
+      "tls_pins" => self.tls_pins = parse_list(value, origin)?,
+      "tls_insecure" => self.tls_insecure = parse_value(value, origin)?,
+      "proxy" => self.proxy = Some(parse_value(value, origin)?),
+      "pools" => self.pools = parse_list(value, origin)?,
+      "splits" => self.splits = parse_json(value, origin)?,
+      "mode" => self.mode = parse_value(value, origin)?,
+      "name" => self.name = value.into(),
+      "device_id" => self.device_id = Some(parse_value(value, origin)?),
+      "hashrate" => self.hashrate = Some(parse_value(value, origin)?),
+      "devices" => self.devices = parse_list(value, origin)?,
+      "memory" => self.memory = parse_list(value, origin)?,
+      "profiles" => self.profiles = parse_json(value, origin)?,
+      "overrides" => self.overrides = parse_json(value, origin)?,
+      "db_path" => self.db_path = Some(value.into()),
+      "db_size" => self.db_size = parse_value(value, origin)?,
+      "db_max" => self.db_max = parse_value(value, origin)?,
+      _ => Err(Error::Config(origin.into(), format!("unknown key `{}`", key)))?,
+    }
+
+    Ok(())
+  }
+
+  /// Returns the key pair of `payout_key`, if one is configured.
+  pub fn key_pair(&self) -> Result<Option<KeyPair>, Error> {
+    let key: &str = match self.payout_key {
+      Some(ref key) => key,
+      None => return Ok(None),
+    };
+
+    let bytes: Vec<u8> =
+      decode(key).map_err(|error| Error::Config("payout_key".into(), format!("invalid hex: {}", error)))?;
+
+    let key: PrivateKey = PrivateKey::deserialize_from_vec(&bytes)
+      .map_err(|error| Error::Config("payout_key".into(), format!("invalid private key: {:?}", error)))?;
+
+    Ok(Some(KeyPair::from(key)))
+  }
+
+  /// Returns the configured `device_id`, or the one stored in `db_path`.
   #[inline]
-  pub fn wsurl(&self) -> Url {
-    Url::parse(&format!("wss://{}:{}", self.host, self.port)).unwrap()
+  pub fn device_id(&self) -> Result<u32, Error> {
+    match self.device_id {
+      Some(device_id) => Ok(device_id),
+      None => load_device_id(&self.db_path(), None),
+    }
+  }
+
+  /// Returns the configured `db_path`, or a directory of its own for the
+  /// network, so chains of different networks are never mixed up.
+  pub fn db_path(&self) -> String {
+    match self.db_path {
+      Some(ref db_path) => db_path.clone(),
+      None => format!("./db/{}", self.network),
+    }
+  }
+
+  /// Returns the start difficulty for one share every `DESIRED_SPS` seconds,
+  /// from the configured `hashrate`, else the `measured` one (H/s).
+  pub fn difficulty(&self, measured: Option<f64>) -> u32 {
+    let hashrate: f64 = match (self.hashrate, measured) {
+      (Some(hashrate), _) => f64::from(hashrate) * 1000.0,
+      (None, Some(measured)) => measured,
+      (None, None) => f64::from(DEFAULT_HASHRATE) * 1000.0,
+    };
+
+    ((hashrate * f64::from(DESIRED_SPS)) / f64::from(1 << 16)).max(1.0) as u32
+  }
+
+  /// Returns the pools by priority, `host` and `port` if no `pools` are set.
+  pub fn endpoints(&self) -> Vec<PoolEndpoint> {
+    if self.pools.is_empty() {
+      vec![PoolEndpoint::new(&self.host, self.port)]
+    } else {
+      self.pools.clone()
+    }
+  }
+
+  /// Checks the config against the OpenCL `devices` of this machine.
+  ///
+  /// Every problem is reported at once as `Error::Invalid`, along with the key
+  /// it came from.
+  pub fn validate(&self, devices: &[Device]) -> Result<(), Error> {
+    let mut errors: Vec<(String, String)> = Vec::new();
+
+    if self.address.trim().is_empty() {
+      errors.push(missing("address"));
+    } else if let Err(error) = Address::from_user_friendly_address(&self.address) {
+      errors.push((
+        "address".into(),
+        format!("invalid address `{}`: {:?}", self.address, error),
+      ));
+    }
+
+    match self.key_pair() {
+      Ok(Some(pair)) => match Address::from_user_friendly_address(&self.address) {
+        Ok(ref address) if *address != Address::from(&pair.public) => {
+          errors.push(("payout_key".into(), "does not belong to `address`".into()));
+        }
+        _ => {}
+      },
+      Ok(None) => {}
+      Err(Error::Config(key, message)) => errors.push((key, message)),
+      Err(error) => errors.push(("payout_key".into(), format!("{:?}", error))),
+    }
+
+    // A list of pools makes `host` and `port` optional, solo miners need none
+    if self.pools.is_empty() && self.mode != PoolMode::Solo {
+      if self.host.trim().is_empty() {
+        errors.push(missing("host"));
+      } else if let Err(Error::Custom(message)) = PoolEndpoint::new(&self.host, self.port).wsurl(self.scheme) {
+        errors.push(("host".into(), message));
+      }
+
+      if self.port == 0 {
+        errors.push(missing("port"));
+      }
+    }
+
+    for (position, endpoint) in self.pools.iter().enumerate() {
+      if endpoint.port == 0 {
+        errors.push((format!("pools[{}]", position), "port must not be 0".into()));
+      } else if let Err(Error::Custom(message)) = endpoint.wsurl(self.scheme) {
+        errors.push((format!("pools[{}]", position), message));
+      }
+    }
+
+    if let Some(ref proxy) = self.proxy {
+      for endpoint in self.endpoints() {
+        if let Err(message) = proxy.check_host(&endpoint.host) {
+          errors.push(("proxy".into(), format!("cannot tunnel to {}: {}", endpoint, message)));
+        }
+      }
+    }
+
+    if self.tls_ca.is_some() {
+      match TlsConfig::from(self).connector() {
+        Ok(_) => {}
+        Err(Error::ConfigFile(path, error)) => {
+          errors.push(("tls_ca".into(), format!("cannot read `{}`: {}", path.display(), error)));
+        }
+        Err(error) => errors.push(("tls_ca".into(), format!("{:?}", error))),
+      }
+    }
+
+    for (position, pin) in self.tls_pins.iter().enumerate() {
+      if normalize_pin(pin).is_none() {
+        errors.push((
+          format!("tls_pins[{}]", position),
+          format!("expected a hex SHA-256 fingerprint, found `{}`", pin),
+        ));
+      }
+    }
+
+    for (position, split) in self.splits.iter().enumerate() {
+      if let Err(error) = Address::from_user_friendly_address(&split.address) {
+        errors.push((
+          format!("splits[{}].address", position),
+          format!("invalid address `{}`: {:?}", split.address, error),
+        ));
+      }
+
+      match split.pool {
+        Some(ref endpoint) if endpoint.port == 0 => {
+          errors.push((format!("splits[{}].pool", position), "port must not be 0".into()));
+        }
+        Some(ref endpoint) => {
+          if let Err(Error::Custom(message)) = endpoint.wsurl(self.scheme) {
+            errors.push((format!("splits[{}].pool", position), message));
+          }
+        }
+        None => {}
+      }
+    }
+
+    if !self.splits.is_empty() && self.splits.iter().all(|split| split.weight == 0) {
+      errors.push(("splits".into(), "at least one weight must be greater than 0".into()));
+    }
+
+    if self.hashrate == Some(0) {
+      errors.push(("hashrate".into(), "must be greater than 0".into()));
+    }
+
+    for (position, index) in self.devices.iter().enumerate() {
+      if !devices.iter().any(|device| device.index == *index) {
+        errors.push((
+          format!("devices[{}]", position),
+          format!("no OpenCL device #{} ({} devices found)", index, devices.len()),
+        ));
+      }
+    }
+
+    if self.memory.len() > 1 && !self.devices.is_empty() && self.memory.len() != self.devices.len() {
+      errors.push((
+        "memory".into(),
+        format!(
+          "expected 1 or {} entries, found {}",
+          self.devices.len(),
+          self.memory.len()
+        ),
+      ));
+    }
+
+    let config: MinerConfig = MinerConfig::from(self);
+
+    let enabled = devices.iter().filter(|device| config.allowed_device(device.index));
+
+    for (position, device) in enabled.enumerate() {
+      let tuning: Tuning = config.tuning(device, position);
+
+      // The defaults fit the device
+      if tuning.memory.unwrap_or(0) == 0 && tuning.global_size.is_none() {
+        continue;
+      }
+
+      let (_, size) = blocks_memory(device, &tuning);
+      let overrides: Option<&Tuning> = self.overrides.get(&device.index);
+
+      let key: String = if tuning.global_size.is_some() {
+        if overrides.and_then(|tuning| tuning.global_size).is_some() {
+          format!("overrides.{}.global_size", device.index)
+        } else {
+          "profiles".into()
+        }
+      } else if overrides.and_then(|tuning| tuning.memory).is_some() {
+        format!("overrides.{}.memory", device.index)
+      } else if self.memory.len() == 1 {
+        "memory".into()
+      } else if position < self.memory.len() {
+        format!("memory[{}]", position)
+      } else {
+        "profiles".into()
+      };
+
+      if size > device.max_mem_alloc_size {
+        errors.push((
+          key,
+          format!(
+            "{} MB exceeds CL_DEVICE_MAX_MEM_ALLOC_SIZE of device #{} ({} MB)",
+            size / ONE_MB,
+            device.index,
+            device.max_mem_alloc_size / ONE_MB
+          ),
+        ));
+      } else if size > device.global_mem_size {
+        errors.push((
+          key,
+          format!(
+            "{} MB exceeds CL_DEVICE_GLOBAL_MEM_SIZE of device #{} ({} MB)",
+            size / ONE_MB,
+            device.index,
+            device.global_mem_size / ONE_MB
+          ),
+        ));
+      }
+    }
+
+    if errors.is_empty() {
+      Ok(())
+    } else {
+      Err(Error::Invalid(errors))
+    }
   }
 }
 
@@ -46,21 +593,268 @@ impl Default for PoolConfig {
   #[inline]
   fn default() -> Self {
     Self {
-      address: "NQ33 G0T9 D63A TMN2 S18B 960Q S3Q3 TMYD KQJE",
-      // host: "eu.sushipool.com",
-      // host: "us.nimpool.io",
-      host: "pool.nimiq.watch",
-      // port: 443,
-      // port: 8444,
-      port: 8443,
-      name: "My Miner", // os.hostname();
-      hashrate: 100,    // 100 kH/s by default
+      network: NetworkName::Main,
+      // Required
+      address: String::new(),
+      payout_key: None,
+      // Required without `pools`, eg. "pool.nimiq.watch"
+      host: String::new(),
+      // Required without `pools`, eg. 8443
+      port: 0,
+      scheme: PoolScheme::Wss,
+      tls_ca: None,
+      tls_pins: Vec::new(),
+      tls_insecure: false,
+      proxy: None,
+      pools: Vec::new(),
+      splits: Vec::new(),
+      mode: PoolMode::Nano,
+      name: "My Miner".into(), // os.hostname();
+      device_id: None,
+      hashrate: None, // Measured by default
       devices: vec![0],
-      memory: vec![2048],
+      // Derived from each device unless set
+      memory: Vec::new(),
+      profiles: Vec::new(),
+      overrides: BTreeMap::new(),
 
-      db_path: "./db/",
+      db_path: None,
       db_size: 1024 * 1024 * 50,
       db_max: 10,
     }
   }
 }
+
+fn parse_value<T: FromStr>(value: &str, origin: &str) -> Result<T, Error> {
+  value
+    .trim()
+    .parse()
+    .map_err(|_| Error::Config(origin.into(), format!("invalid value `{}`", value)))
+}
+
+fn parse_json<T: DeserializeOwned>(value: &str, origin: &str) -> Result<T, Error> {
+  from_str(value).map_err(|error| Error::Config(origin.into(), error.to_string()))
+}
+
+fn parse_list<T: FromStr>(value: &str, origin: &str) -> Result<Vec<T>, Error> {
+  value
+    .split(',')
+    .filter(|item| !item.trim().is_empty())
+    .map(|item| parse_value(item, origin))
+    .collect()
+}
+
+// Keeps the private key out of `--print-effective-config`.
+fn redact<S: Serializer>(key: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+  match key {
+    Some(_) => serializer.serialize_str("<redacted>"),
+    None => serializer.serialize_none(),
+  }
+}
+
+#[inline]
+fn missing(key: &str) -> (String, String) {
+  (key.into(), "missing required key".into())
+}
+
+// Removes `key` from `object`, invalid values are added to `errors`.
+fn optional<T: DeserializeOwned>(
+  object: &mut Map<String, Value>,
+  key: &str,
+  errors: &mut Vec<(String, String)>,
+) -> Option<T> {
+  match object.remove(key) {
+    Some(Value::Null) | None => None,
+    Some(value) => match from_value(value) {
+      Ok(value) => Some(value),
+      Err(error) => {
+        errors.push((key.into(), error.to_string()));
+        None
+      }
+    },
+  }
+}
+
+// The shipped config stores the port as a string, accept both forms.
+fn port(object: &mut Map<String, Value>, key: &str, errors: &mut Vec<(String, String)>) -> Option<u16> {
+  let port: Result<u16, String> = match object.remove(key) {
+    Some(Value::Null) | None => return None,
+    Some(Value::String(ref port)) => port.trim().parse().map_err(|_| format!("invalid port `{}`", port)),
+    Some(value) => from_value(value).map_err(|error| error.to_string()),
+  };
+
+  match port {
+    Ok(port) => Some(port),
+    Err(message) => {
+      errors.push((key.into(), message));
+      None
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::env::remove_var;
+  use std::env::set_var;
+
+  use super::*;
+
+  const ADDRESS: &'static str = "NQ33 G0T9 D63A TMN2 S18B 960Q S3Q3 TMYD KQJE";
+
+  // Keys of the problems reported by `result`, sorted
+  fn keys(result: Result<(), Error>) -> Vec<String> {
+    let mut keys: Vec<String> = match result {
+      Err(Error::Invalid(errors)) => errors.into_iter().map(|(key, _)| key).collect(),
+      Err(Error::Config(key, _)) => vec![key],
+      result => panic!("expected config errors, got {:?}", result),
+    };
+
+    keys.sort();
+    keys
+  }
+
+  #[test]
+  fn parse_with_comments() {
+    let config: PoolConfig = PoolConfig::parse(
+      r#"{
+        // The pool
+        "host": "pool.example.com", /* with its port */
+        "port": "8443",
+        "name": "rig // 1"
+      }"#,
+    )
+    .unwrap();
+
+    assert_eq!(config.host, "pool.example.com");
+    assert_eq!(config.port, 8443);
+    assert_eq!(config.name, "rig // 1");
+  }
+
+  #[test]
+  fn parse_defaults_missing_keys() {
+    assert_eq!(PoolConfig::parse("{}").unwrap(), PoolConfig::default());
+  }
+
+  #[test]
+  fn parse_reports_every_problem() {
+    let input: &str = r#"{ "port": "https", "hashrate": "fast", "name": "rig", "hots": "x", "fee": 1 }"#;
+    let (config, errors) = PoolConfig::parse_partial(input).unwrap();
+
+    let mut invalid: Vec<String> = errors.into_iter().map(|(key, _)| key).collect();
+
+    invalid.sort();
+
+    assert_eq!(invalid, vec!["fee", "hashrate", "hots", "port"]);
+
+    // Valid keys are still read, invalid ones keep their default
+    assert_eq!(config.name, "rig");
+    assert_eq!(config.port, 0);
+    assert_eq!(config.hashrate, None);
+
+    assert_eq!(keys(PoolConfig::parse(input).map(|_| ())), invalid);
+  }
+
+  #[test]
+  fn parse_rejects_malformed_files() {
+    assert!(PoolConfig::parse(r#"{ "host": "#).is_err());
+    assert_eq!(keys(PoolConfig::parse("[]").map(|_| ())), vec!["<root>"]);
+  }
+
+  #[test]
+  fn set_parses_values() {
+    let mut config: PoolConfig = PoolConfig::default();
+
+    config.set("devices", "0, 2,", "--devices").unwrap();
+    config.set("memory", "4096,2048", "--memory").unwrap();
+    config.set("port", " 443 ", "--port").unwrap();
+    config.set("mode", "smart", "--mode").unwrap();
+
+    assert_eq!(config.devices, vec![0, 2]);
+    assert_eq!(config.memory, vec![4096, 2048]);
+    assert_eq!(config.port, 443);
+    assert_eq!(config.mode, PoolMode::Smart);
+  }
+
+  #[test]
+  fn set_names_the_origin() {
+    let mut config: PoolConfig = PoolConfig::default();
+
+    assert_eq!(keys(config.set("port", "none", "--port")), vec!["--port"]);
+    assert_eq!(
+      keys(config.set("devices", "0,x", "PLUTONIUM_DEVICES")),
+      vec!["PLUTONIUM_DEVICES"]
+    );
+    assert_eq!(keys(config.set("hots", "x", "--hots")), vec!["--hots"]);
+  }
+
+  // The only test setting `PLUTONIUM_*` variables, tests run in parallel
+  #[test]
+  fn file_env_and_flag_precedence() {
+    let input: &str = r#"{ "name": "file", "host": "file.example.com", "port": 1 }"#;
+    let mut config: PoolConfig = PoolConfig::parse(input).unwrap();
+
+    set_var("PLUTONIUM_NAME", "env");
+    set_var("PLUTONIUM_PORT", "2");
+
+    let merged: Result<(), Error> = config.merge_env();
+
+    remove_var("PLUTONIUM_NAME");
+    remove_var("PLUTONIUM_PORT");
+
+    merged.unwrap();
+    config.set("port", "3", "--port").unwrap();
+
+    assert_eq!(config.host, "file.example.com");
+    assert_eq!(config.name, "env");
+    assert_eq!(config.port, 3);
+
+    set_var("PLUTONIUM_HASHRATE", "fast");
+    set_var("PLUTONIUM_DEVICE_ID", "rig");
+
+    let merged: Result<(), Error> = config.merge_env();
+
+    remove_var("PLUTONIUM_HASHRATE");
+    remove_var("PLUTONIUM_DEVICE_ID");
+
+    assert_eq!(keys(merged), vec!["PLUTONIUM_DEVICE_ID", "PLUTONIUM_HASHRATE"]);
+  }
+
+  #[test]
+  fn validate_requires_keys() {
+    let config = PoolConfig {
+      devices: Vec::new(),
+      ..PoolConfig::default()
+    };
+
+    assert_eq!(keys(config.validate(&[])), vec!["address", "host", "port"]);
+  }
+
+  #[test]
+  fn validate_accepts_pools_instead_of_host() {
+    let config = PoolConfig {
+      address: ADDRESS.into(),
+      pools: vec![PoolEndpoint::new("pool.example.com", 8443)],
+      devices: Vec::new(),
+      ..PoolConfig::default()
+    };
+
+    assert!(config.validate(&[]).is_ok());
+  }
+
+  #[test]
+  fn validate_reports_every_problem() {
+    let config = PoolConfig {
+      address: "NQ00 invalid".into(),
+      host: "pool.example.com".into(),
+      port: 8443,
+      tls_pins: vec!["not a pin".into()],
+      hashrate: Some(0),
+      ..PoolConfig::default()
+    };
+
+    assert_eq!(
+      keys(config.validate(&[])),
+      vec!["address", "devices[0]", "hashrate", "tls_pins[0]"]
+    );
+  }
+}
@@ -0,0 +1,113 @@
+use beserial::Serialize;
+use byteorder::BigEndian;
+use byteorder::ByteOrder;
+use nimiq_hash::Blake2bHash;
+use nimiq_hash::Blake2bHasher;
+use nimiq_hash::Hasher;
+use nimiq_keys::PrivateKey;
+use rand::random;
+use std::fs::create_dir_all;
+use std::fs::read_to_string;
+use std::fs::write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::config::MINER_VERSION;
+use crate::config::ONE_MB;
+use crate::error::Error;
+use crate::miner::Worker;
+use crate::pool::PoolConfig;
+
+const DEVICE_ID_FILE: &'static str = "pool_device_id";
+const DEVICE_ID_PREFIX: &'static [u8] = b"pool_device_id";
+
+/// Derives a device id from `seed`, like `BasePoolMiner.generateDeviceId`
+/// does from the private key of the node.
+pub fn generate_device_id(seed: &[u8]) -> u32 {
+  let mut bytes: Vec<u8> = Vec::with_capacity(DEVICE_ID_PREFIX.len() + seed.len());
+
+  bytes.extend_from_slice(DEVICE_ID_PREFIX);
+  bytes.extend_from_slice(seed);
+
+  let hash: Blake2bHash = Blake2bHasher::default().digest(&bytes);
+
+  BigEndian::read_u32(hash.as_bytes())
+}
+
+/// Returns the device id stored in the data directory `dir`.
+///
+/// If there is none yet, it is derived from `key` (or a random seed) and
+/// stored, so it stays the same after restarts.
+pub fn load_device_id<P: AsRef<Path>>(dir: P, key: Option<&PrivateKey>) -> Result<u32, Error> {
+  let path: PathBuf = dir.as_ref().join(DEVICE_ID_FILE);
+
+  if path.exists() {
+    let content: String = read_to_string(&path)?;
+
+    return content
+      .trim()
+      .parse()
+      .map_err(|_| format!("Invalid device id in {}: `{}`", path.display(), content.trim()).into());
+  }
+
+  let device: u32 = match key {
+    Some(key) => generate_device_id(&key.serialize_to_vec()),
+    None => generate_device_id(&random::<[u8; 32]>()),
+  };
+
+  create_dir_all(dir.as_ref())?;
+  write(&path, device.to_string())?;
+
+  Ok(device)
+}
+
+/// The `deviceData` of the register message, shown on the pool dashboard.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceData {
+  pub device_name: String,
+  pub miner_version: String,
+  pub start_difficulty: u32,
+  pub gpus: Vec<GpuInfo>,
+}
+
+/// A GPU of the rig, as collected by its `Worker`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GpuInfo {
+  pub index: u32,
+  pub name: String,
+  pub vendor: String,
+  pub driver: String,
+  pub compute_units: u32,
+  /// Max clock frequency in MHz
+  pub clock_frequency: u32,
+  /// Global memory in Mb
+  pub memory: u64,
+}
+
+impl DeviceData {
+  /// Collects the data of the rig, `hashrate` is the measured one (H/s).
+  pub fn new(config: &PoolConfig, gpus: Vec<GpuInfo>, hashrate: Option<f64>) -> Self {
+    Self {
+      device_name: config.name.clone(),
+      miner_version: MINER_VERSION.into(),
+      start_difficulty: config.difficulty(hashrate),
+      gpus,
+    }
+  }
+}
+
+impl<'a> From<&'a Worker> for GpuInfo {
+  fn from(worker: &'a Worker) -> Self {
+    Self {
+      index: worker.device_index,
+      name: worker.device_name.clone(),
+      vendor: worker.device_vendor.clone(),
+      driver: worker.driver_version.clone(),
+      compute_units: worker.max_compute_units,
+      clock_frequency: worker.max_clock_frequency,
+      memory: worker.global_mem_size / ONE_MB,
+    }
+  }
+}
@@ -0,0 +1,153 @@
+use serde::de::Error as _;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::net::SocketAddr;
+use std::net::TcpStream;
+use std::net::ToSocketAddrs;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::runtime::current_thread;
+use tokio::util::FutureExt;
+use url::Url;
+
+use crate::error::Error;
+use crate::pool::open_tunnel;
+use crate::pool::PoolProxy;
+
+/// Websocket scheme of pool connections.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PoolScheme {
+  /// Plaintext, eg. for a pool proxy on the local network
+  Ws,
+  /// TLS
+  Wss,
+}
+
+impl Default for PoolScheme {
+  #[inline]
+  fn default() -> Self {
+    PoolScheme::Wss
+  }
+}
+
+impl Display for PoolScheme {
+  fn fmt(&self, f: &mut Formatter) -> FmtResult {
+    match self {
+      PoolScheme::Ws => f.write_str("ws"),
+      PoolScheme::Wss => f.write_str("wss"),
+    }
+  }
+}
+
+impl FromStr for PoolScheme {
+  type Err = String;
+
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    match value.to_lowercase().as_str() {
+      "ws" => Ok(PoolScheme::Ws),
+      "wss" => Ok(PoolScheme::Wss),
+      _ => Err(format!("unknown scheme `{}`", value)),
+    }
+  }
+}
+
+/// A pool server, written as `host:port` or `scheme://host:port`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PoolEndpoint {
+  /// Overrides the configured `scheme` if set
+  pub scheme: Option<PoolScheme>,
+  pub host: String,
+  pub port: u16,
+}
+
+impl PoolEndpoint {
+  #[inline]
+  pub fn new(host: &str, port: u16) -> Self {
+    Self {
+      scheme: None,
+      host: host.into(),
+      port,
+    }
+  }
+
+  /// Returns the websocket url, using `scheme` unless the endpoint has its own.
+  #[inline]
+  pub fn wsurl(&self, scheme: PoolScheme) -> Result<Url, Error> {
+    let url: String = format!("{}://{}:{}", self.scheme.unwrap_or(scheme), self.host, self.port);
+
+    Url::parse(&url).map_err(|error| format!("invalid pool url `{}`: {}", url, error).into())
+  }
+
+  /// Checks whether a TCP connection to the pool can be opened within
+  /// `timeout`, or a tunnel through `proxy` if set.
+  pub fn is_reachable(&self, proxy: Option<&PoolProxy>, timeout: Duration) -> bool {
+    if let Some(proxy) = proxy {
+      let tunnel = open_tunnel(proxy.clone(), self.host.clone(), self.port).timeout(timeout);
+
+      return current_thread::block_on_all(tunnel).is_ok();
+    }
+
+    let addresses: Vec<SocketAddr> = match (self.host.as_str(), self.port).to_socket_addrs() {
+      Ok(addresses) => addresses.collect(),
+      Err(_) => return false,
+    };
+
+    addresses
+      .iter()
+      .any(|address| TcpStream::connect_timeout(address, timeout).is_ok())
+  }
+}
+
+impl Display for PoolEndpoint {
+  fn fmt(&self, f: &mut Formatter) -> FmtResult {
+    if let Some(scheme) = self.scheme {
+      write!(f, "{}://", scheme)?;
+    }
+
+    write!(f, "{}:{}", self.host, self.port)
+  }
+}
+
+impl FromStr for PoolEndpoint {
+  type Err = String;
+
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    let (scheme, address): (Option<PoolScheme>, &str) = match value.trim().find("://") {
+      Some(index) => (Some(value.trim()[..index].parse()?), &value.trim()[index + 3..]),
+      None => (None, value.trim()),
+    };
+
+    let mut parts = address.rsplitn(2, ':');
+
+    match (parts.next(), parts.next()) {
+      (Some(port), Some(host)) if !host.is_empty() => port
+        .parse()
+        .map(|port| Self {
+          scheme,
+          ..Self::new(host, port)
+        })
+        .map_err(|_| format!("invalid port in `{}`", value)),
+      _ => Err(format!("expected `host:port`, found `{}`", value)),
+    }
+  }
+}
+
+impl Serialize for PoolEndpoint {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(self)
+  }
+}
+
+impl<'de> Deserialize<'de> for PoolEndpoint {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let value: String = String::deserialize(deserializer)?;
+
+    value.parse().map_err(D::Error::custom)
+  }
+}
@@ -0,0 +1,15 @@
+use std::time::Duration;
+use url::Url;
+
+/// Connection state changes of a `PoolClient`, see `PoolClient::notifier`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PoolEvent {
+  /// Opening a websocket to the pool
+  Connecting(Url),
+  /// The handshake completed and the register message is on its way
+  Connected(Url),
+  /// The connection was lost or could not be established, with the reason
+  Disconnected(String),
+  /// Waiting before the next connection attempt
+  Reconnecting(Duration),
+}
@@ -0,0 +1,220 @@
+use crate::pool::PoolEndpoint;
+
+// Failed connection attempts before switching to the next pool
+const MAX_FAILURES: u32 = 3;
+// Rejected shares before switching, if they are the majority
+const MAX_REJECTED: u64 = 10;
+
+/// Tracks the health of the active pool and picks the pool to switch to.
+///
+/// Pools are ordered by priority, the first one is preferred.
+#[derive(Debug)]
+pub struct Failover {
+  endpoints: Vec<PoolEndpoint>,
+  active: usize,
+  failures: u32,
+  rejected: u64,
+  // Shares submitted before switching to the active pool
+  submitted: u64,
+}
+
+impl Failover {
+  /// Creates the failover of `endpoints`, which must not be empty.
+  pub fn new(endpoints: Vec<PoolEndpoint>) -> Self {
+    Self {
+      endpoints,
+      active: 0,
+      failures: 0,
+      rejected: 0,
+      submitted: 0,
+    }
+  }
+
+  #[inline]
+  pub fn active(&self) -> &PoolEndpoint {
+    &self.endpoints[self.active]
+  }
+
+  #[inline]
+  pub fn index(&self) -> usize {
+    self.active
+  }
+
+  /// Pools with a higher priority than the active one, to probe for a fail back.
+  #[inline]
+  pub fn preferred(&self) -> &[PoolEndpoint] {
+    &self.endpoints[..self.active]
+  }
+
+  #[inline]
+  pub fn connected(&mut self) {
+    self.failures = 0;
+  }
+
+  /// Records a failed connection attempt. Returns the pool to switch to once
+  /// the active one stays unreachable.
+  pub fn failed(&mut self) -> Option<usize> {
+    self.failures += 1;
+
+    if self.failures >= MAX_FAILURES {
+      self.next()
+    } else {
+      None
+    }
+  }
+
+  /// Records a rejected share, `submitted` being the total of submitted
+  /// shares. Returns the pool to switch to once the active one rejects most
+  /// of them.
+  pub fn rejected(&mut self, submitted: u64) -> Option<usize> {
+    self.rejected += 1;
+
+    if self.rejected >= MAX_REJECTED && self.rejected * 2 > submitted.saturating_sub(self.submitted) {
+      self.next()
+    } else {
+      None
+    }
+  }
+
+  /// Makes the pool at `index` the active one.
+  pub fn switch(&mut self, index: usize, submitted: u64) -> &PoolEndpoint {
+    self.active = index;
+    self.failures = 0;
+    self.rejected = 0;
+    self.submitted = submitted;
+    self.active()
+  }
+
+  #[inline]
+  fn next(&self) -> Option<usize> {
+    if self.endpoints.len() > 1 {
+      Some((self.active + 1) % self.endpoints.len())
+    } else {
+      None
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn failover(count: u16) -> Failover {
+    Failover::new(
+      (0..count)
+        .map(|index| PoolEndpoint::new("pool.example.com", 8000 + index))
+        .collect(),
+    )
+  }
+
+  #[test]
+  fn fails_over_after_failed_attempts() {
+    let mut failover: Failover = failover(3);
+
+    assert_eq!(failover.failed(), None);
+    assert_eq!(failover.failed(), None);
+    assert_eq!(failover.failed(), Some(1));
+  }
+
+  #[test]
+  fn connecting_resets_failures() {
+    let mut failover: Failover = failover(2);
+
+    failover.failed();
+    failover.failed();
+    failover.connected();
+
+    assert_eq!(failover.failed(), None);
+    assert_eq!(failover.failed(), None);
+    assert_eq!(failover.failed(), Some(1));
+  }
+
+  #[test]
+  fn wraps_around_to_the_first_pool() {
+    let mut failover: Failover = failover(3);
+
+    assert_eq!(failover.switch(2, 0).port, 8002);
+
+    failover.failed();
+    failover.failed();
+
+    assert_eq!(failover.failed(), Some(0));
+  }
+
+  #[test]
+  fn keeps_a_single_pool() {
+    let mut failover: Failover = failover(1);
+
+    for _ in 0..MAX_FAILURES * 2 {
+      assert_eq!(failover.failed(), None);
+    }
+
+    for submitted in 1..=MAX_REJECTED * 2 {
+      assert_eq!(failover.rejected(submitted), None);
+    }
+  }
+
+  #[test]
+  fn fails_over_when_most_shares_are_rejected() {
+    let mut failover: Failover = failover(2);
+
+    for _ in 1..MAX_REJECTED {
+      assert_eq!(failover.rejected(15), None);
+    }
+
+    assert_eq!(failover.rejected(15), Some(1));
+  }
+
+  #[test]
+  fn tolerates_a_minority_of_rejected_shares() {
+    let mut failover: Failover = failover(2);
+
+    for _ in 0..MAX_REJECTED {
+      assert_eq!(failover.rejected(100), None);
+    }
+  }
+
+  #[test]
+  fn counts_shares_since_the_switch() {
+    let mut failover: Failover = failover(3);
+
+    failover.switch(1, 100);
+
+    for _ in 1..MAX_REJECTED {
+      assert_eq!(failover.rejected(115), None);
+    }
+
+    // 10 of the 15 shares submitted to this pool
+    assert_eq!(failover.rejected(115), Some(2));
+  }
+
+  #[test]
+  fn switching_resets_the_counters() {
+    let mut failover: Failover = failover(2);
+
+    failover.failed();
+    failover.failed();
+    failover.switch(1, 0);
+
+    assert_eq!(failover.index(), 1);
+    assert_eq!(failover.failed(), None);
+  }
+
+  #[test]
+  fn prefers_pools_of_higher_priority() {
+    let mut failover: Failover = failover(3);
+
+    assert!(failover.preferred().is_empty());
+
+    failover.switch(2, 0);
+
+    let ports: Vec<u16> = failover.preferred().iter().map(|endpoint| endpoint.port).collect();
+
+    // Probed in the order of their priority
+    assert_eq!(ports, vec![8000, 8001]);
+
+    failover.switch(0, 0);
+
+    assert!(failover.preferred().is_empty());
+  }
+}
@@ -0,0 +1,232 @@
+use nimiq_hash::Blake2bHash;
+use nimiq_hash::Blake2bHasher;
+use nimiq_hash::Hasher;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MerklePathNode {
+  pub hash: Blake2bHash,
+  pub left: bool,
+}
+
+/// Inclusion proof of a single leaf in a Merkle tree, eg. the `minerAddr` of a
+/// block body.
+///
+/// Mirrors `MerklePath` of the JS client, which is what the pool verifies.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MerklePath {
+  nodes: Vec<MerklePathNode>,
+}
+
+impl MerklePath {
+  /// Computes the path of `leaf` in the tree of the hashed `values`.
+  pub fn compute(values: &[Blake2bHash], leaf: &Blake2bHash) -> Self {
+    let mut nodes: Vec<MerklePathNode> = Vec::new();
+
+    compute(values, leaf, &mut nodes);
+
+    Self { nodes }
+  }
+
+  #[inline]
+  pub fn nodes(&self) -> &[MerklePathNode] {
+    &self.nodes
+  }
+
+  /// Computes the root of the tree from the hash of the leaf.
+  pub fn root(&self, leaf: &Blake2bHash) -> Blake2bHash {
+    self.nodes.iter().fold(leaf.clone(), |root, node| {
+      if node.left {
+        concat(&node.hash, &root)
+      } else {
+        concat(&root, &node.hash)
+      }
+    })
+  }
+
+  /// Serializes the path as the node count, a bitmap of left nodes (MSB
+  /// first) and the node hashes.
+  pub fn serialize_to_vec(&self) -> Vec<u8> {
+    let count: usize = self.nodes.len();
+    let mut left: Vec<u8> = vec![0; (count + 7) / 8];
+
+    for (index, node) in self.nodes.iter().enumerate() {
+      if node.left {
+        left[index / 8] |= 0x80 >> (index % 8);
+      }
+    }
+
+    let mut bytes: Vec<u8> = Vec::with_capacity(1 + left.len() + count * 32);
+
+    bytes.push(count as u8);
+    bytes.extend_from_slice(&left);
+
+    for node in self.nodes.iter() {
+      bytes.extend_from_slice(node.hash.as_bytes());
+    }
+
+    bytes
+  }
+}
+
+// Returns whether `values` contain `leaf`, along with the root of `values`.
+fn compute(values: &[Blake2bHash], leaf: &Blake2bHash, nodes: &mut Vec<MerklePathNode>) -> (bool, Blake2bHash) {
+  match values.len() {
+    0 => (false, Blake2bHasher::default().digest(&[])),
+    1 => (values[0] == *leaf, values[0].clone()),
+    len => {
+      // The left half gets the extra value, like `Math.round(len / 2)`
+      let (lvalues, rvalues) = values.split_at((len + 1) / 2);
+      let (lleaf, lhash) = compute(lvalues, leaf, nodes);
+      let (rleaf, rhash) = compute(rvalues, leaf, nodes);
+      let hash: Blake2bHash = concat(&lhash, &rhash);
+
+      if lleaf {
+        nodes.push(MerklePathNode {
+          hash: rhash,
+          left: false,
+        });
+      } else if rleaf {
+        nodes.push(MerklePathNode {
+          hash: lhash,
+          left: true,
+        });
+      }
+
+      (lleaf || rleaf, hash)
+    }
+  }
+}
+
+#[inline]
+fn concat(left: &Blake2bHash, right: &Blake2bHash) -> Blake2bHash {
+  let mut bytes: Vec<u8> = Vec::with_capacity(64);
+
+  bytes.extend_from_slice(left.as_bytes());
+  bytes.extend_from_slice(right.as_bytes());
+
+  Blake2bHasher::default().digest(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+  use hex::encode;
+  use nimiq_block::BlockBody;
+  use nimiq_keys::Address;
+
+  use super::*;
+
+  // The vectors follow `MerklePath.compute` and `MerklePath.serialize` of the
+  // JS client, which the pool verifies shares with.
+
+  // Leaves of bytes 0 to 4, an odd count splits unevenly
+  fn leaves() -> Vec<Blake2bHash> {
+    (0..5u8)
+      .map(|value| Blake2bHasher::default().digest(&[value]))
+      .collect()
+  }
+
+  fn body() -> BlockBody {
+    let mut miner: [u8; 20] = [0; 20];
+
+    for (index, byte) in miner.iter_mut().enumerate() {
+      *byte = index as u8 + 1;
+    }
+
+    BlockBody {
+      miner: Address::from(miner),
+      extra_data: b"plutonium".to_vec(),
+      transactions: Vec::new(),
+      receipts: Vec::new(),
+    }
+  }
+
+  #[test]
+  fn path_of_first_leaf() {
+    let leaves: Vec<Blake2bHash> = leaves();
+    let path: MerklePath = MerklePath::compute(&leaves, &leaves[0]);
+
+    assert_eq!(
+      encode(path.serialize_to_vec()),
+      "0300\
+       ee155ace9c40292074cb6aff8c9ccdd273c81648ff1149ef36bcea6ebb8a3e25\
+       bb30a42c1e62f0afda5f0a4e8a562f7a13a24cea00ee81917b86b89e801314aa\
+       479e3ed183ec4594f36c38d1251a145c77c91503b680c0796c27bf6e3d0b2ae3"
+    );
+  }
+
+  #[test]
+  fn path_of_second_leaf() {
+    let leaves: Vec<Blake2bHash> = leaves();
+    let path: MerklePath = MerklePath::compute(&leaves, &leaves[1]);
+
+    assert_eq!(
+      encode(path.serialize_to_vec()),
+      "0380\
+       03170a2e7597b7b7e3d84c05391d139a62b157e78786d8c082f29dcf4c111314\
+       bb30a42c1e62f0afda5f0a4e8a562f7a13a24cea00ee81917b86b89e801314aa\
+       479e3ed183ec4594f36c38d1251a145c77c91503b680c0796c27bf6e3d0b2ae3"
+    );
+  }
+
+  #[test]
+  fn path_of_last_leaf() {
+    let leaves: Vec<Blake2bHash> = leaves();
+    let path: MerklePath = MerklePath::compute(&leaves, &leaves[4]);
+
+    assert_eq!(
+      encode(path.serialize_to_vec()),
+      "02c0\
+       e88bd757ad5b9bedf372d8d3f0cf6c962a469db61a265f6418e1ffed86da29ec\
+       3f8fc66b1fc7006260eb087b7745362cd8d64b9983d89ad0281f03e8dc492464"
+    );
+  }
+
+  #[test]
+  fn paths_lead_to_the_root() {
+    let leaves: Vec<Blake2bHash> = leaves();
+
+    for leaf in leaves.iter() {
+      let root: Blake2bHash = MerklePath::compute(&leaves, leaf).root(leaf);
+
+      assert_eq!(
+        encode(root.as_bytes()),
+        "cfd113df11628bd8679f3218b853844c9ca404e5599e1c4f4c9b7549e7f92670"
+      );
+    }
+  }
+
+  #[test]
+  fn miner_addr_proof() {
+    let leaves: Vec<Blake2bHash> = body().get_merkle_leaves::<Blake2bHash>();
+    let path: MerklePath = MerklePath::compute(&leaves, &leaves[0]);
+
+    assert_eq!(
+      encode(path.serialize_to_vec()),
+      "0100ac3bc68fec28d8196d5b2b11c98612f0f4b72183b1a3af56ec2a89ff046a1c2b"
+    );
+    assert_eq!(
+      encode(path.root(&leaves[0]).as_bytes()),
+      "ee513dd5afd4d1eace300e20133b6e428f903fa6aa7424a8184b6281958e0d94"
+    );
+  }
+
+  #[test]
+  fn extra_data_proof() {
+    let leaves: Vec<Blake2bHash> = body().get_merkle_leaves::<Blake2bHash>();
+    let path: MerklePath = MerklePath::compute(&leaves, &leaves[1]);
+
+    assert_eq!(
+      encode(path.serialize_to_vec()),
+      "0180877a567036d56c98c42ea9a05d739b5537423d24411579286fd93816d5e296c7"
+    );
+    assert_eq!(
+      encode(path.root(&leaves[1]).as_bytes()),
+      "ee513dd5afd4d1eace300e20133b6e428f903fa6aa7424a8184b6281958e0d94"
+    );
+  }
+
+  #[test]
+  fn empty_path() {
+    assert_eq!(MerklePath::default().serialize_to_vec(), vec![0]);
+  }
+}
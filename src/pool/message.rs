@@ -1,10 +1,37 @@
-use std::collections::HashMap;
+use base64::encode;
+use beserial::Serialize;
+use byteorder::BigEndian;
+use byteorder::ByteOrder;
+use nimiq_block::Block;
+use nimiq_block::BlockBody;
+use nimiq_hash::Blake2bHash;
+use nimiq_keys::KeyPair;
+use nimiq_keys::Signature;
+use serde_json::from_str;
+use serde_json::from_value;
+use serde_json::Value;
 
+use crate::error::Error;
+use crate::pool::DeviceData;
+use crate::pool::MerklePath;
 use crate::pool::PoolMode;
 
+const PAYOUT_PREFIX: &'static [u8] = b"POOL_PAYOUT";
+
+// Message types of the pool protocol, others are parsed as `Unknown`
+const MESSAGES: [&'static str; 8] = [
+  "register",
+  "registered",
+  "settings",
+  "new-block",
+  "share",
+  "error",
+  "balance",
+  "payout",
+];
+
 #[derive(Debug, Deserialize, Serialize)]
 #[serde(tag = "message")]
-#[serde(deny_unknown_fields)]
 pub enum PoolMessage {
   /// Send by client directly after connecting to the server.
   ///
@@ -22,7 +49,7 @@ pub enum PoolMessage {
     /// A JSON object including stats about the device. The format
     /// of this JSON should be defined by the pool operator
     #[serde(rename = "deviceData")]
-    data: Option<HashMap<String, String>>,
+    data: Option<DeviceData>,
     /// Base64 encoded hash of the genesis block used by the client
     #[serde(rename = "genesisHash")]
     genesis: String,
@@ -87,6 +114,7 @@ pub enum PoolMessage {
     extra_data_proof: String,
     /// Base64 encoded full block. May only be sent if the block is
     /// a valid block so that the pool server is faster in picking it up
+    #[serde(skip_serializing_if = "Option::is_none")]
     block: Option<String>,
   },
   /// Sent by the server if the client sent an invalid share.
@@ -124,4 +152,210 @@ pub enum PoolMessage {
     /// concatenated with the byte representation of the connection nonce.
     proof: String,
   },
+  /// A message type this client does not know, eg. from a newer protocol
+  /// version.
+  #[serde(skip)]
+  Unknown {
+    /// The `message` field
+    message: String,
+    /// The whole message
+    raw: Value,
+  },
+}
+
+impl PoolMessage {
+  /// Parses a message received from the pool.
+  ///
+  /// Fields this client does not know are ignored, unknown message types
+  /// are returned as `Unknown`.
+  pub fn parse(text: &str) -> Result<Self, Error> {
+    let raw: Value = from_str(text)?;
+
+    let message: String = match raw.get("message").and_then(Value::as_str) {
+      Some(message) => message.into(),
+      None => Err("Missing `message` field")?,
+    };
+
+    if MESSAGES.contains(&message.as_str()) {
+      Ok(from_value(raw)?)
+    } else {
+      Ok(PoolMessage::Unknown { message, raw })
+    }
+  }
+
+  /// Builds a `payout` request for the connection `nonce` of the last
+  /// `settings` message, signed with the key pair of the registered address.
+  pub fn payout(pair: &KeyPair, nonce: u64) -> Self {
+    let mut data: Vec<u8> = Vec::with_capacity(PAYOUT_PREFIX.len() + 8);
+
+    data.extend_from_slice(PAYOUT_PREFIX);
+    data.extend_from_slice(&[0; 8]);
+
+    BigEndian::write_u64(&mut data[PAYOUT_PREFIX.len()..], nonce);
+
+    let signature: Signature = pair.sign(&data);
+
+    // A single-signature proof: public key, empty Merkle path, signature
+    let mut proof: Vec<u8> = pair.public.serialize_to_vec();
+
+    proof.extend(MerklePath::default().serialize_to_vec());
+    proof.extend(signature.serialize_to_vec());
+
+    PoolMessage::Payout { proof: encode(&proof) }
+  }
+
+  /// Builds the `share` message of a mined `block`.
+  ///
+  /// Light blocks are sent as nano shares. Full blocks are sent as smart
+  /// shares, with the full block only if it meets the network target.
+  pub fn share(block: &Block) -> Self {
+    match block.body {
+      Some(ref body) => Self::share_smart(block, body),
+      None => PoolMessage::ShareNano {
+        block: encode(&block.serialize_to_vec()),
+      },
+    }
+  }
+
+  fn share_smart(block: &Block, body: &BlockBody) -> Self {
+    // minerAddr and extraData are the first two leaves of the body
+    let leaves: Vec<Blake2bHash> = body.get_merkle_leaves::<Blake2bHash>();
+
+    let full: Option<String> = if block.header.verify_proof_of_work() {
+      Some(encode(&block.serialize_to_vec()))
+    } else {
+      None
+    };
+
+    PoolMessage::ShareSmart {
+      block_header: encode(&block.header.serialize_to_vec()),
+      miner_addr_proof: encode(&MerklePath::compute(&leaves, &leaves[0]).serialize_to_vec()),
+      extra_data_proof: encode(&MerklePath::compute(&leaves, &leaves[1]).serialize_to_vec()),
+      block: full,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use base64::decode;
+  use beserial::Deserialize;
+  use nimiq_keys::PrivateKey;
+  use nimiq_keys::PublicKey;
+
+  use super::*;
+
+  const NONCE: u64 = 0x0102_0304_0506_0708;
+
+  fn pair() -> KeyPair {
+    KeyPair::from(PrivateKey::deserialize_from_vec(&vec![0x11; 32]).unwrap())
+  }
+
+  fn proof(message: PoolMessage) -> Vec<u8> {
+    match message {
+      PoolMessage::Payout { proof } => decode(&proof).unwrap(),
+      message => panic!("expected a payout message, got {:?}", message),
+    }
+  }
+
+  #[test]
+  fn payout_proof_layout() {
+    let pair: KeyPair = pair();
+    let proof: Vec<u8> = proof(PoolMessage::payout(&pair, NONCE));
+
+    // Public key, a Merkle path without nodes, signature
+    assert_eq!(proof.len(), PublicKey::SIZE + 1 + Signature::SIZE);
+    assert_eq!(&proof[..PublicKey::SIZE], &pair.public.serialize_to_vec()[..]);
+    assert_eq!(proof[PublicKey::SIZE], 0);
+  }
+
+  #[test]
+  fn payout_signature() {
+    let pair: KeyPair = pair();
+    let proof: Vec<u8> = proof(PoolMessage::payout(&pair, NONCE));
+
+    let public: PublicKey = PublicKey::deserialize_from_vec(&proof[..PublicKey::SIZE].to_vec()).unwrap();
+    let signature: Signature = Signature::deserialize_from_vec(&proof[PublicKey::SIZE + 1..].to_vec()).unwrap();
+
+    let mut data: Vec<u8> = b"POOL_PAYOUT".to_vec();
+
+    // The nonce is big-endian
+    data.extend_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+
+    assert!(public.verify(&signature, &data));
+
+    data[PAYOUT_PREFIX.len()] = 0;
+
+    assert!(!public.verify(&signature, &data));
+  }
+
+  #[test]
+  fn parse_known_with_extra_fields() {
+    let text: &str = r#"{
+      "message": "settings",
+      "address": "NQ07 0000 0000 0000 0000 0000 0000 0000 0000",
+      "extraData": "",
+      "targetCompact": 486604799,
+      "nonce": 42,
+      "motd": "from a newer pool"
+    }"#;
+
+    match PoolMessage::parse(text).unwrap() {
+      PoolMessage::Settings { target, nonce, .. } => {
+        assert_eq!(target, 486_604_799);
+        assert_eq!(nonce, 42);
+      }
+      message => panic!("expected settings, got {:?}", message),
+    }
+  }
+
+  #[test]
+  fn parse_error_with_extra_fields() {
+    let text: &str = r#"{"message": "error", "reason": "invalid share: invalid pow", "code": 7}"#;
+
+    match PoolMessage::parse(text).unwrap() {
+      PoolMessage::Error { reason } => assert_eq!(reason, "invalid share: invalid pow"),
+      message => panic!("expected an error, got {:?}", message),
+    }
+  }
+
+  #[test]
+  fn parse_unknown_message() {
+    let text: &str = r#"{"message": "stats", "hashrate": 1000}"#;
+
+    match PoolMessage::parse(text).unwrap() {
+      PoolMessage::Unknown { message, raw } => {
+        assert_eq!(message, "stats");
+        assert_eq!(raw["hashrate"], 1000);
+      }
+      message => panic!("expected an unknown message, got {:?}", message),
+    }
+  }
+
+  #[test]
+  fn parse_malformed() {
+    assert!(PoolMessage::parse(r#"{"message": "settings""#).is_err());
+    assert!(PoolMessage::parse("").is_err());
+    assert!(PoolMessage::parse("[1, 2]").is_err());
+  }
+
+  #[test]
+  fn parse_without_message() {
+    assert!(PoolMessage::parse(r#"{"reason": "no type"}"#).is_err());
+    assert!(PoolMessage::parse(r#"{"message": 1}"#).is_err());
+  }
+
+  #[test]
+  fn parse_invalid_fields() {
+    assert!(PoolMessage::parse(r#"{"message": "error"}"#).is_err());
+    assert!(PoolMessage::parse(r#"{"message": "balance", "balance": "a lot"}"#).is_err());
+  }
+
+  #[test]
+  fn payout_message() {
+    let value: Value = serde_json::to_value(PoolMessage::payout(&pair(), NONCE)).unwrap();
+
+    assert_eq!(value["message"], "payout");
+    assert!(value["proof"].is_string());
+  }
 }
@@ -1,43 +1,93 @@
 use base64::decode;
 use beserial::Deserialize;
 use nimiq_block::Block;
+use nimiq_block::BlockHeader;
 use nimiq_block::BlockInterlink;
 use nimiq_block::Target;
 use nimiq_blockchain::Blockchain;
 use nimiq_hash::Blake2bHash;
 use nimiq_hash::Hash;
+use nimiq_keys::Address;
+use nimiq_keys::KeyPair;
+use nimiq_mempool::Mempool;
 use nimiq_network::Network;
-use nimiq_network_primitives::networks::get_network_info;
-use nimiq_network_primitives::networks::NetworkInfo;
-use nimiq_primitives::networks::NetworkId;
+use std::sync::mpsc::channel;
+use std::sync::mpsc::Sender;
 use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread::spawn;
+use std::time::Duration;
+use std::time::Instant;
 
 use crate::error::Error;
+use crate::miner::Found;
+use crate::miner::Miner;
+use crate::miner::MinerConfig;
+use crate::miner::HASH_METER;
 use crate::miner::MINER;
+use crate::pool::publish_block;
+use crate::pool::BlockTemplate;
+use crate::pool::DeviceData;
+use crate::pool::Failover;
+use crate::pool::GpuInfo;
 use crate::pool::PoolChain;
 use crate::pool::PoolClient;
 use crate::pool::PoolConfig;
+use crate::pool::PoolEndpoint;
+use crate::pool::PoolEvent;
 use crate::pool::PoolMessage;
+use crate::pool::PoolMode;
+use crate::pool::PoolProxy;
+use crate::pool::ShareLog;
+use crate::pool::ShareReport;
+use crate::pool::Split;
+use crate::pool::SplitSchedule;
+use crate::pool::TlsConfig;
+use crate::pool::DESIRED_SPS;
 
 type ArcChain = Arc<Blockchain<'static>>;
 type ArcNetwork = Arc<Network>;
+type ArcMempool = Arc<Mempool<'static>>;
 
 const TAG: &'static str = "PoolMiner";
 
+// Benchmark per device for the first start difficulty
+const MEASURE_TIME: Duration = Duration::from_secs(5);
+// Share rates off by more than this factor trigger a new registration
+const MAX_DRIFT: f64 = 2.0;
+// Fewer expected shares are too noisy to judge the share rate
+const MIN_EXPECTED_SHARES: f64 = 20.0;
+// Seconds pool blocks may be ahead of the network time
+const MAX_TIMESTAMP_DRIFT: u64 = 600;
+
 pub struct PoolMiner {
   blockchain: ArcChain,
   network: ArcNetwork,
   config: PoolConfig,
+  client: PoolClient,
+  work: Mutex<Sender<Block>>,
+  shares: Arc<Mutex<ShareLog>>,
+  template: BlockTemplate,
+  gpus: Vec<GpuInfo>,
+  failover: Failover,
+  splits: Option<SplitSchedule>,
   enabled: bool, // _miningEnabled
   connected: bool,
-  hashrate: u32, // _hashrate
+  hashrate: Option<f64>, // _hashrate
+  difficulty: u32,
+  drift: (Instant, u64),
   pool: Option<PoolChain>,
+  pool_address: Option<Address>, // _poolAddress
+  extra_data: Vec<u8>,           // _extraData
+  nonce: Option<u64>,            // _nonce
+  payout_requested: bool,
 }
 
 impl std::fmt::Debug for PoolMiner {
   fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
     f.debug_struct("PoolMiner")
       .field("config", &self.config)
+      .field("template", &self.template)
       .field("enabled", &self.enabled)
       .field("connected", &self.connected)
       .field("hashrate", &self.hashrate)
@@ -46,23 +96,219 @@ impl std::fmt::Debug for PoolMiner {
 }
 
 impl PoolMiner {
-  pub fn new(blockchain: ArcChain, network: ArcNetwork, config: PoolConfig) -> Self {
-    let _ = &*MINER;
+  pub fn new(
+    blockchain: ArcChain,
+    network: ArcNetwork,
+    mempool: ArcMempool,
+    config: PoolConfig,
+  ) -> Result<Self, Error> {
+    if config.mode == PoolMode::Solo {
+      Err(Error::Config("mode".into(), "solo mode mines without a pool".into()))?
+    }
+
+    let (gpus, hashrate): (Vec<GpuInfo>, Option<f64>) = {
+      let mut miner = MINER.write().unwrap();
+
+      miner.configure(MinerConfig::from(&config));
+      miner.initialize()?;
+
+      let hashrate: Option<f64> = if config.hashrate.is_none() {
+        Self::measure(&mut miner, &blockchain)?
+      } else {
+        None
+      };
+
+      (miner.workers().iter().map(GpuInfo::from).collect(), hashrate)
+    };
+
+    let failover: Failover = Failover::new(config.endpoints());
+    let splits: Option<SplitSchedule> = Self::schedule(&config);
+    let (registration, endpoint) = Self::registration(&config, &failover, splits.as_ref());
 
-    Self {
+    let data: DeviceData = DeviceData::new(&config, gpus.clone(), hashrate);
+    let difficulty: u32 = data.start_difficulty;
+
+    let client: PoolClient = PoolClient::new(&registration, &endpoint, data)?;
+    let shares: Arc<Mutex<ShareLog>> = Arc::default();
+
+    shares.lock().unwrap().set_pool(endpoint.to_string());
+
+    let work: Sender<Block> = Self::spawn_worker(Arc::clone(&blockchain), client.clone(), Arc::clone(&shares));
+    let template: BlockTemplate = BlockTemplate::new(Arc::clone(&blockchain), mempool, config.network.genesis_hash());
+
+    Ok(Self {
+      failover,
+      splits,
       blockchain,
       network,
       config,
+      client,
+      work: Mutex::new(work),
+      shares,
+      template,
+      gpus,
       enabled: false,
       connected: false,
-      hashrate: 0,
+      hashrate,
+      difficulty,
+      drift: (Instant::now(), 0),
       pool: None,
-    }
+      pool_address: None,
+      extra_data: Vec::new(),
+      nonce: None,
+      payout_requested: false,
+    })
   }
 
   #[inline]
   pub fn client(&self) -> PoolClient {
-    PoolClient::new(&self.config).unwrap()
+    self.client.clone()
+  }
+
+  // Mines blocks on a separate thread so pool messages keep flowing.
+  // Found shares are submitted right away, full blocks that meet the network
+  // target are also pushed into the local blockchain.
+  fn spawn_worker(blockchain: ArcChain, client: PoolClient, shares: Arc<Mutex<ShareLog>>) -> Sender<Block> {
+    let (sender, receiver) = channel::<Block>();
+
+    spawn(move || {
+      while let Ok(mut block) = receiver.recv() {
+        // Skip outdated work
+        while let Ok(next) = receiver.try_recv() {
+          block = next;
+        }
+
+        let submit = |device: u32, found: Found| {
+          let block: Block = match found {
+            Found::Share(block) => block,
+            Found::Stale(_) => return shares.lock().unwrap().stale(device),
+          };
+
+          let nonce: u32 = block.header.nonce;
+
+          if !shares.lock().unwrap().found(device, &block) {
+            eprintln!("[{}] Duplicate Share: {}", TAG, nonce);
+            return;
+          }
+
+          match client.send(&PoolMessage::share(&block)) {
+            Ok(()) => shares.lock().unwrap().submit(device, nonce),
+            Err(error) => {
+              eprintln!("[{}] Share Error: {} - {:?}", TAG, nonce, error);
+
+              shares.lock().unwrap().unsent(device);
+            }
+          }
+
+          // A slow or lost pool must not cost a block
+          if block.header.verify_proof_of_work() {
+            if block.body.is_some() {
+              println!("[{}] Found Block #{}, relaying it as well", TAG, block.header.height);
+
+              publish_block(Arc::clone(&blockchain), block);
+            } else {
+              println!(
+                "[{}] Found Block #{}, its body is only known to the pool",
+                TAG, block.header.height
+              );
+            }
+          }
+        };
+
+        if let Err(error) = MINER.write().unwrap().mine(block, Arc::clone(&blockchain), submit) {
+          eprintln!("[{}] Mining Error: {:?}", TAG, error);
+        }
+      }
+    });
+
+    sender
+  }
+
+  /// Applies a new config without restarting the node.
+  ///
+  /// New pools or a new address reconnect and re-register, new
+  /// `devices`/`memory` rebuild the affected GPU workers.
+  pub fn reload(&mut self, mut config: PoolConfig) -> Result<(), Error> {
+    if config == self.config {
+      println!("[{}] Config unchanged", TAG);
+      return Ok(());
+    }
+
+    let endpoints: bool = config.endpoints() != self.config.endpoints();
+
+    let reconnect: bool = endpoints
+      || config.scheme != self.config.scheme
+      || config.proxy != self.config.proxy
+      || TlsConfig::from(&config) != TlsConfig::from(&self.config)
+      || config.mode != self.config.mode
+      || config.device_id != self.config.device_id
+      || config.address != self.config.address
+      || config.splits != self.config.splits;
+
+    let rebuild: bool = config.devices != self.config.devices
+      || config.memory != self.config.memory
+      || config.profiles != self.config.profiles
+      || config.overrides != self.config.overrides;
+
+    if config.db_path() != self.config.db_path()
+      || config.db_size != self.config.db_size
+      || config.db_max != self.config.db_max
+    {
+      eprintln!("[{}] Database settings are only applied on restart", TAG);
+    }
+
+    // The blockchain belongs to the network it was started with
+    if config.network != self.config.network {
+      eprintln!("[{}] Network is only applied on restart", TAG);
+
+      config.network = self.config.network;
+    }
+
+    if config.mode == PoolMode::Solo {
+      eprintln!("[{}] Solo mode is only applied on restart", TAG);
+
+      config.mode = self.config.mode;
+    }
+
+    if rebuild {
+      println!("[{}] Rebuilding GPU workers", TAG);
+
+      Miner::interrupt();
+
+      let mut miner = MINER.write().unwrap();
+
+      miner.reconfigure(MinerConfig::from(&config))?;
+
+      self.gpus = miner.workers().iter().map(GpuInfo::from).collect();
+
+      HASH_METER.lock().unwrap().clear();
+    }
+
+    // Sent when registering again
+    let data: DeviceData = DeviceData::new(&config, self.gpus.clone(), self.hashrate);
+
+    self.difficulty = data.start_difficulty;
+    self.client.set_device_data(data);
+
+    if endpoints {
+      self.failover = Failover::new(config.endpoints());
+    }
+
+    if config.splits != self.config.splits {
+      self.splits = Self::schedule(&config);
+    }
+
+    self.config = config;
+
+    if reconnect {
+      self.reconnect()?;
+    }
+
+    if rebuild && !reconnect {
+      self.start_mining();
+    }
+
+    Ok(())
   }
 
   pub fn connect(&mut self) {
@@ -82,16 +328,291 @@ impl PoolMiner {
     self.start_mining();
   }
 
+  /// Pools with a higher priority than the active one, by index.
+  pub fn preferred_pools(&self) -> Vec<(usize, PoolEndpoint)> {
+    self.failover.preferred().iter().cloned().enumerate().collect()
+  }
+
+  /// Registers again with the start difficulty of the measured hashrate if
+  /// the share rate drifted far from one share every `DESIRED_SPS` seconds.
+  pub fn check_difficulty(&mut self) -> Result<(), Error> {
+    let submitted: u64 = self.shares.lock().unwrap().submitted();
+    let (since, base) = self.drift;
+
+    self.drift = (Instant::now(), submitted);
+
+    if let Some(hashrate) = HASH_METER.lock().unwrap().total() {
+      self.hashrate = Some(hashrate);
+    }
+
+    // Idle time would count as missing shares
+    if !self.enabled || self.config.hashrate.is_some() {
+      return Ok(());
+    }
+
+    let expected: f64 = since.elapsed().as_secs() as f64 / f64::from(DESIRED_SPS);
+    let shares: u64 = submitted - base;
+
+    if expected < MIN_EXPECTED_SHARES {
+      return Ok(());
+    }
+
+    let ratio: f64 = shares as f64 / expected;
+
+    if ratio < 1.0 / MAX_DRIFT || ratio > MAX_DRIFT {
+      let data: DeviceData = DeviceData::new(&self.config, self.gpus.clone(), self.hashrate);
+
+      println!(
+        "[{}] {} shares submitted, {:.0} expected at {:.2} kH/s",
+        TAG,
+        shares,
+        expected,
+        self.hashrate.unwrap_or_default() / 1000.0
+      );
+
+      // The pool keeps its own difficulty then
+      if data.start_difficulty == self.difficulty {
+        return Ok(());
+      }
+
+      println!(
+        "[{}] Registering again with difficulty {} (was {})",
+        TAG, data.start_difficulty, self.difficulty
+      );
+
+      self.difficulty = data.start_difficulty;
+      self.client.set_device_data(data);
+
+      self.reconnect()?;
+    }
+
+    Ok(())
+  }
+
+  // Benchmarks the workers briefly, the hashrate is not known before mining.
+  fn measure(miner: &mut Miner, blockchain: &ArcChain) -> Result<Option<f64>, Error> {
+    println!("[{}] Measuring hashrate", TAG);
+
+    let hashrates: Vec<(u32, f64)> = miner.benchmark(&*blockchain.head(), MEASURE_TIME)?;
+    let total: f64 = hashrates.iter().map(|(_, hashrate)| hashrate).sum();
+
+    println!("[{}] - hashrate = {:.2} kH/s", TAG, total / 1000.0);
+
+    Ok(Some(total).filter(|total| *total > 0.0))
+  }
+
+  /// Proxy of the pool connections, if any.
+  #[inline]
+  pub fn proxy(&self) -> Option<PoolProxy> {
+    self.config.proxy.clone()
+  }
+
+  /// Switches to the pool at `index` of the configured pools.
+  pub fn switch_pool(&mut self, index: usize) -> Result<(), Error> {
+    let submitted: u64 = self.shares.lock().unwrap().submitted();
+    let endpoint: PoolEndpoint = self.failover.switch(index, submitted).clone();
+
+    println!("[{}] Switching to pool #{} ({})", TAG, index, endpoint);
+
+    self.reconnect()
+  }
+
+  /// Accounts `elapsed` mining time to the active split, and registers the
+  /// split furthest behind its weight once the active one mined long enough.
+  pub fn rotate_split(&mut self, elapsed: Duration) -> Result<(), Error> {
+    let enabled: bool = self.enabled;
+
+    let index: usize = match self.splits {
+      Some(ref mut splits) => {
+        // Time spent connecting or without work does not count
+        if enabled {
+          splits.record(elapsed);
+        }
+
+        match splits.next() {
+          Some(index) => index,
+          None => return Ok(()),
+        }
+      }
+      None => return Ok(()),
+    };
+
+    for (split, realized) in self.split_report() {
+      println!(
+        "[{}] Split {}: {:.1}% (weight {})",
+        TAG,
+        split.address,
+        realized * 100.0,
+        split.weight
+      );
+    }
+
+    let split: Split = self.splits.as_mut().unwrap().switch(index).clone();
+
+    println!("[{}] Rotating to split #{} ({})", TAG, index, split.address);
+
+    self.reconnect()
+  }
+
+  /// Realized share of the mining time (0 to 1) of each split within the
+  /// rolling window, empty if no `splits` are configured.
+  pub fn split_report(&self) -> Vec<(Split, f64)> {
+    match self.splits {
+      Some(ref splits) => splits.splits().iter().cloned().zip(splits.realized()).collect(),
+      None => Vec::new(),
+    }
+  }
+
+  /// Share counters, in total and by device and pool.
+  pub fn share_report(&self) -> ShareReport {
+    self.shares.lock().unwrap().report()
+  }
+
+  // Registers again with the active pool or split, new shares count for it.
+  fn reconnect(&mut self) -> Result<(), Error> {
+    let (registration, endpoint) = Self::registration(&self.config, &self.failover, self.splits.as_ref());
+
+    println!("[{}] Connecting to {}", TAG, endpoint);
+
+    self.reset();
+    self.shares.lock().unwrap().set_pool(endpoint.to_string());
+    self.client.connect(&registration, &endpoint)
+  }
+
+  fn schedule(config: &PoolConfig) -> Option<SplitSchedule> {
+    if config.splits.is_empty() {
+      None
+    } else {
+      Some(SplitSchedule::new(config.splits.clone()))
+    }
+  }
+
+  // The config to register with and the pool to connect to, those of the
+  // active split if the mining time is split.
+  fn registration(
+    config: &PoolConfig,
+    failover: &Failover,
+    splits: Option<&SplitSchedule>,
+  ) -> (PoolConfig, PoolEndpoint) {
+    let mut config: PoolConfig = config.clone();
+    let mut endpoint: PoolEndpoint = failover.active().clone();
+
+    if let Some(split) = splits.map(SplitSchedule::active) {
+      config.address = split.address.clone();
+
+      if let Some(ref pool) = split.pool {
+        endpoint = pool.clone();
+      }
+    }
+
+    (config, endpoint)
+  }
+
+  fn fail_over(&mut self, index: usize, reason: &str) {
+    eprintln!("[{}] Pool {} is {}, failing over", TAG, self.failover.active(), reason);
+
+    if let Err(error) = self.switch_pool(index) {
+      eprintln!("[{}] Failover Error: {:?}", TAG, error);
+    }
+  }
+
+  /// Pauses mining while the pool is unreachable. Once reconnected, the pool
+  /// sends new settings and work.
+  pub fn pool_event(&mut self, event: &PoolEvent) {
+    match event {
+      PoolEvent::Connected(url) => {
+        println!("[{}] Connected to {}", TAG, url);
+
+        self.failover.connected();
+      }
+      PoolEvent::Disconnected(reason) => {
+        eprintln!("[{}] Pool unreachable: {}", TAG, reason);
+
+        if self.enabled {
+          println!("[{}] Pausing GPU work", TAG);
+        }
+
+        self.reset();
+
+        if let Some(index) = self.failover.failed() {
+          self.fail_over(index, "unreachable");
+        }
+      }
+      PoolEvent::Reconnecting(delay) => {
+        println!("[{}] Reconnecting in {}ms", TAG, delay.as_millis());
+      }
+      PoolEvent::Connecting(_) => {}
+    }
+  }
+
+  /// Asks the pool to pay out the confirmed balance, signed with the
+  /// `payout_key`. The next `balance` message confirms the request.
+  pub fn request_payout(&mut self) -> Result<(), Error> {
+    let pair: KeyPair = self
+      .config
+      .key_pair()?
+      .ok_or_else(|| Error::Config("payout_key".into(), "required for payout requests".into()))?;
+
+    if self.splits.is_some() {
+      Err(Error::Config(
+        "splits".into(),
+        "payout requests are not supported while splitting".into(),
+      ))?
+    }
+
+    let nonce: u64 = self.nonce.ok_or("No pool settings received yet")?;
+
+    self.client.send(&PoolMessage::payout(&pair, nonce))?;
+    self.payout_requested = true;
+
+    println!("[{}] Payout requested", TAG);
+
+    Ok(())
+  }
+
+  /// Rebuilds the block of a smart miner on top of the new local head.
+  pub fn head_changed(&mut self) {
+    if self.enabled && self.config.mode == PoolMode::Smart {
+      self.start_mining();
+    }
+  }
+
+  /// Builds a new block if the mempool changed significantly, so the fees of
+  /// new transactions are collected.
+  pub fn mempool_changed(&mut self) {
+    if self.enabled && self.config.mode == PoolMode::Smart && self.template.is_outdated() {
+      println!("[{}] Mempool changed, rebuilding block", TAG);
+
+      self.start_mining();
+    }
+  }
+
   pub fn process(&mut self, message: PoolMessage) -> Result<(), Error> {
     let mut stop: bool = false;
+    let mut start: bool = false;
 
     match message {
+      PoolMessage::NewBlock { .. } if self.config.mode == PoolMode::Smart => {
+        println!("[{}] Ignoring pool block in smart mode", TAG);
+      }
       PoolMessage::NewBlock {
         body,
         accounts,
         previous,
       } => {
-        let previous: Block = Block::deserialize_from_vec(&mut decode(&previous)?)?;
+        let (previous, bhash, ahash) = match self.pool_block(&previous, &body, &accounts) {
+          Ok(block) => block,
+          Err(reason) => {
+            eprintln!("[{}] Invalid base block from pool server: {}", TAG, reason);
+
+            // Mined blocks would be worthless
+            self.pool = None;
+            self.stop_mining();
+
+            return Ok(());
+          }
+        };
+
         let previous_hash: Blake2bHash = previous.header.hash();
         let head_hash: Blake2bHash = self.blockchain.head_hash();
         let head_block: &Block = &*self.blockchain.head();
@@ -166,9 +687,11 @@ impl PoolMiner {
             next_interlink,
             next_target: next,
             prev_block: previous,
-            ahash: Blake2bHash::deserialize_from_vec(&mut decode(&accounts)?)?,
-            bhash: Blake2bHash::deserialize_from_vec(&mut decode(&body)?)?,
+            ahash,
+            bhash,
           });
+
+          start = true;
         }
       }
       PoolMessage::Registered => {
@@ -187,7 +710,20 @@ impl PoolMiner {
         println!("[+] - target  = {}", target);
         println!("[+] - nonce   = {}", nonce);
 
+        // Used by smart miners to build their own blocks
+        self.pool_address = Some(
+          Address::from_user_friendly_address(&address)
+            .map_err(|error| Error::from(format!("Invalid pool address `{}`: {:?}", address, error)))?,
+        );
+        self.extra_data = decode(&extra)?;
+        self.nonce = Some(nonce);
+
+        // Release the miner lock held by the mining thread
+        Miner::interrupt();
+
         MINER.write().unwrap().scompact(target);
+
+        start = true;
       }
       PoolMessage::Balance {
         balance,
@@ -198,9 +734,49 @@ impl PoolMiner {
         println!("[+] - current   = {}", balance);
         println!("[+] - confirmed = {}", confirmed);
         println!("[+] - payout    = {}", payout);
+
+        if self.payout_requested {
+          self.payout_requested = false;
+
+          if payout {
+            println!("[{}] Payout request confirmed", TAG);
+          } else {
+            eprintln!("[{}] Payout request not active", TAG);
+          }
+        }
       }
       PoolMessage::Error { reason } => {
-        eprintln!("[x] Pool Error: {}", reason);
+        let mut shares = self.shares.lock().unwrap();
+        let rejected: Option<u32> = shares.reject(&reason);
+
+        match rejected {
+          Some(nonce) => eprintln!("[x] Share Rejected: {} - {}", nonce, reason),
+          None => eprintln!("[x] Pool Error: {}", reason),
+        }
+
+        let report: ShareReport = shares.report();
+        let submitted: u64 = shares.submitted();
+
+        drop(shares);
+
+        println!(
+          "[{}] Shares: {} found, {} accepted, {} rejected, {} stale, {} duplicate",
+          TAG,
+          report.total.found,
+          report.total.accepted,
+          report.total.rejected_total(),
+          report.total.stale,
+          report.total.duplicate
+        );
+
+        if rejected.is_some() {
+          if let Some(index) = self.failover.rejected(submitted) {
+            self.fail_over(index, "rejecting shares");
+          }
+        }
+      }
+      PoolMessage::Unknown { message, raw } => {
+        println!("[{}] Ignoring Pool Message `{}`: {}", TAG, message, raw);
       }
       message => {
         println!("[{}] Unknown Pool Message: {:#?}", TAG, message);
@@ -209,47 +785,164 @@ impl PoolMiner {
 
     if stop {
       self.stop_mining();
-    } else {
+    } else if start {
       self.start_mining();
     }
 
     Ok(())
   }
 
-  fn start_mining(&mut self) {
-    if self.pool.is_none() {
-      return;
+  // Decodes the block the pool mines on and the hashes of the next body and
+  // accounts, and checks them as far as they can be without the body. Its
+  // parent and target are only checked if the parent is known locally.
+  fn pool_block(
+    &self,
+    previous: &str,
+    body: &str,
+    accounts: &str,
+  ) -> Result<(Block, Blake2bHash, Blake2bHash), String> {
+    let previous: Block = decode(previous)
+      .map_err(Error::from)
+      .and_then(|mut bytes| Ok(Block::deserialize_from_vec(&mut bytes)?))
+      .map_err(|error| format!("malformed previous block: {:?}", error))?;
+
+    let bhash: Blake2bHash = pool_hash(body).map_err(|error| format!("malformed body hash: {:?}", error))?;
+    let ahash: Blake2bHash = pool_hash(accounts).map_err(|error| format!("malformed accounts hash: {:?}", error))?;
+
+    let header: &BlockHeader = &previous.header;
+
+    if !header.verify_proof_of_work() {
+      Err(format!(
+        "proof of work {} does not meet its target {:#010x}",
+        header.pow(),
+        u32::from(header.n_bits)
+      ))?
+    }
+
+    if previous.interlink.hash(self.config.network.genesis_hash()) != header.interlink_hash {
+      Err("interlink does not match the interlink hash")?
+    }
+
+    let now: u64 = self.network.network_time.now() / 1000;
+
+    if u64::from(header.timestamp) > now + MAX_TIMESTAMP_DRIFT {
+      Err(format!(
+        "timestamp is {}s in the future",
+        u64::from(header.timestamp) - now
+      ))?
+    }
+
+    match self.blockchain.get_block(&header.prev_hash, true, false) {
+      Some(parent) => {
+        if !previous.is_immediate_successor_of(&parent) {
+          Err(format!(
+            "not an immediate successor of its parent #{} {}",
+            parent.header.height, header.prev_hash
+          ))?
+        }
+
+        if Target::from(header.n_bits) != self.blockchain.get_next_target(Some(&header.prev_hash)) {
+          Err(format!(
+            "target {:#010x} does not follow its chain",
+            u32::from(header.n_bits)
+          ))?
+        }
+      }
+      // The local chain is behind or on another fork, only a different block
+      // at the same height contradicts the pool
+      None => {
+        let hash: Blake2bHash = header.hash();
+
+        match self.blockchain.get_block_at(header.height, false) {
+          Some(ref block) if block.header.hash::<Blake2bHash>() != hash => Err(format!(
+            "conflicts with the local block #{} {}",
+            header.height,
+            block.header.hash::<Blake2bHash>()
+          ))?,
+          _ => println!(
+            "[{}] Unknown parent {} of block #{}, not checking continuity",
+            TAG, header.prev_hash, header.height
+          ),
+        }
+      }
+    }
+
+    if bhash == Blake2bHash::default() || ahash == Blake2bHash::default() {
+      Err("empty body or accounts hash")?
     }
 
-    let pool: &PoolChain = self.pool.as_ref().unwrap();
+    Ok((previous, bhash, ahash))
+  }
+
+  fn start_mining(&mut self) {
+    let block: Block = match self.next_block() {
+      Some(block) => block,
+      None => return,
+    };
 
     self.enabled = true;
 
-    let info: &NetworkInfo = get_network_info(NetworkId::Main).unwrap();
-    let hash: Blake2bHash = info.genesis_block.header.hash();
+    println!(
+      "[{}] Starting work on block #{} ({:.0} H/s)",
+      TAG,
+      block.header.height,
+      self.hashrate.unwrap_or_default(),
+    );
+
+    Miner::interrupt();
+
+    if self.work.lock().unwrap().send(block).is_err() {
+      eprintln!("[{}] Mining thread stopped", TAG);
+    }
+  }
 
+  // Returns the next block to mine, or `None` until the pool sent enough to
+  // build one.
+  fn next_block(&self) -> Option<Block> {
     let now: u64 = self.network.network_time.now();
     let now: u32 = (now / 1000) as u32;
     let time: u32 = now.max(self.blockchain.head().header.timestamp + 1);
-    let time: u32 = time.max(pool.prev_block.header.timestamp + 1);
 
-    // Construct next block.
-    let block = pool.next(hash, time);
+    match self.config.mode {
+      PoolMode::Nano => {
+        let pool: &PoolChain = self.pool.as_ref()?;
 
-    println!(
-      "[{}] Starting work on block #{} ({} H/s)",
-      TAG, block.header.height, self.hashrate,
-    );
+        let hash: Blake2bHash = self.config.network.genesis_hash();
+        let time: u32 = time.max(pool.prev_block.header.timestamp + 1);
+
+        // Construct next block.
+        Some(pool.next(hash, time))
+      }
+      PoolMode::Smart | PoolMode::Solo => {
+        let address: Address = self.pool_address.clone()?;
+
+        match self.template.next(time, address, self.extra_data.clone()) {
+          Ok(block) => Some(block),
+          Err(error) => {
+            eprintln!("[{}] Template Error: {:?}", TAG, error);
+            None
+          }
+        }
+      }
+    }
+  }
 
-    MINER
-      .write()
-      .unwrap()
-      .mine(block, Arc::clone(&self.blockchain))
-      .unwrap();
+  // Forgets the work and settings of the pool, they are no longer accepted.
+  fn reset(&mut self) {
+    self.pool = None;
+    self.pool_address = None;
+    self.nonce = None;
+    self.payout_requested = false;
+    self.stop_mining();
   }
 
   fn stop_mining(&mut self) {
     self.enabled = false;
-    // TODO: So much...
+
+    Miner::interrupt();
   }
 }
+
+fn pool_hash(value: &str) -> Result<Blake2bHash, Error> {
+  Ok(Blake2bHash::deserialize_from_vec(&mut decode(value)?)?)
+}
@@ -1,15 +1,39 @@
 mod chain;
 mod client;
 mod config;
+mod device;
+mod endpoint;
+mod event;
+mod failover;
+mod merkle;
 mod message;
 mod miner;
 mod mode;
+mod network;
+mod proxy;
+mod shares;
+mod split;
 mod state;
+mod template;
+mod tls;
+mod transport;
 
 pub use self::chain::*;
 pub use self::client::*;
 pub use self::config::*;
+pub use self::device::*;
+pub use self::endpoint::*;
+pub use self::event::*;
+pub use self::failover::*;
+pub use self::merkle::*;
 pub use self::message::*;
 pub use self::miner::*;
 pub use self::mode::*;
+pub use self::network::*;
+pub use self::proxy::*;
+pub use self::shares::*;
+pub use self::split::*;
 pub use self::state::*;
+pub use self::template::*;
+pub use self::tls::*;
+pub use self::transport::*;
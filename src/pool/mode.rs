@@ -1,6 +1,47 @@
-#[derive(Debug, Deserialize, Serialize)]
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::str::FromStr;
+
+/// How blocks are built, and for whom.
+///
+/// Nano clients mine on the block announced by the pool, smart clients build
+/// full blocks from their own blockchain and mempool. Solo clients mine their
+/// own blocks to the network target, without a pool.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum PoolMode {
   Nano,
   Smart,
+  Solo,
+}
+
+impl Default for PoolMode {
+  #[inline]
+  fn default() -> Self {
+    PoolMode::Nano
+  }
+}
+
+impl Display for PoolMode {
+  fn fmt(&self, f: &mut Formatter) -> FmtResult {
+    match self {
+      PoolMode::Nano => f.write_str("nano"),
+      PoolMode::Smart => f.write_str("smart"),
+      PoolMode::Solo => f.write_str("solo"),
+    }
+  }
+}
+
+impl FromStr for PoolMode {
+  type Err = String;
+
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    match value.to_lowercase().as_str() {
+      "nano" => Ok(PoolMode::Nano),
+      "smart" => Ok(PoolMode::Smart),
+      "solo" => Ok(PoolMode::Solo),
+      _ => Err(format!("unknown mode `{}`", value)),
+    }
+  }
 }
@@ -0,0 +1,72 @@
+use nimiq_hash::Blake2bHash;
+use nimiq_hash::Hash;
+use nimiq_network_primitives::networks::get_network_info;
+use nimiq_network_primitives::networks::NetworkInfo;
+use nimiq_primitives::networks::NetworkId;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::str::FromStr;
+
+/// The Nimiq network to mine on.
+///
+/// Selects the seed peers of the node, the genesis hash registered with the
+/// pool and the genesis block of the blocks built for it.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NetworkName {
+  Main,
+  Test,
+  Dev,
+}
+
+impl NetworkName {
+  #[inline]
+  pub fn id(self) -> NetworkId {
+    match self {
+      NetworkName::Main => NetworkId::Main,
+      NetworkName::Test => NetworkId::Test,
+      NetworkName::Dev => NetworkId::Dev,
+    }
+  }
+
+  #[inline]
+  pub fn info(self) -> &'static NetworkInfo {
+    get_network_info(self.id()).unwrap()
+  }
+
+  #[inline]
+  pub fn genesis_hash(self) -> Blake2bHash {
+    self.info().genesis_block.header.hash()
+  }
+}
+
+impl Default for NetworkName {
+  #[inline]
+  fn default() -> Self {
+    NetworkName::Main
+  }
+}
+
+impl Display for NetworkName {
+  fn fmt(&self, f: &mut Formatter) -> FmtResult {
+    match self {
+      NetworkName::Main => f.write_str("main"),
+      NetworkName::Test => f.write_str("test"),
+      NetworkName::Dev => f.write_str("dev"),
+    }
+  }
+}
+
+impl FromStr for NetworkName {
+  type Err = String;
+
+  fn from_str(value: &str) -> Result<Self, Self::Err> {
+    match value.to_lowercase().as_str() {
+      "main" => Ok(NetworkName::Main),
+      "test" => Ok(NetworkName::Test),
+      "dev" => Ok(NetworkName::Dev),
+      _ => Err(format!("unknown network `{}`", value)),
+    }
+  }
+}
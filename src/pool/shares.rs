@@ -0,0 +1,214 @@
+use nimiq_block::Block;
+use nimiq_hash::Blake2bHash;
+use nimiq_hash::Hash;
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::time::Duration;
+use std::time::Instant;
+
+// The pool never acknowledges valid shares, older ones are assumed accepted.
+const PENDING_SHARES: usize = 32;
+const ACCEPT_DELAY: Duration = Duration::from_secs(30);
+// Shares remembered to detect duplicates
+const SEEN_SHARES: usize = 1024;
+// Start of the `error` replies the pool server sends for bad shares, eg.
+// `invalid share: invalid pow`. Other errors are not about a share.
+const SHARE_REJECTION: &str = "invalid share";
+
+/// Share counters of the rig, a device or a pool.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ShareTotals {
+  /// Shares found by the GPUs
+  pub found: u64,
+  /// Shares sent to the pool
+  pub submitted: u64,
+  /// Submitted shares without an `error` reply
+  pub accepted: u64,
+  /// Submitted shares the pool replied an `error` to, by reason
+  pub rejected: BTreeMap<String, u64>,
+  /// Shares of outdated work, not submitted
+  pub stale: u64,
+  /// Shares found twice, not submitted
+  pub duplicate: u64,
+}
+
+impl ShareTotals {
+  /// Returns the number of rejected shares, for any reason.
+  pub fn rejected_total(&self) -> u64 {
+    self.rejected.values().sum()
+  }
+
+  /// Returns the number of submitted shares still waiting for a reply.
+  pub fn pending(&self) -> u64 {
+    self.submitted - self.accepted - self.rejected_total()
+  }
+
+  /// Returns the share of found shares (0 to 1) that were accepted.
+  pub fn accepted_ratio(&self) -> f64 {
+    if self.found == 0 {
+      0.0
+    } else {
+      self.accepted as f64 / self.found as f64
+    }
+  }
+}
+
+/// Share counters, in total and by device index and pool.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ShareReport {
+  pub total: ShareTotals,
+  pub devices: BTreeMap<u32, ShareTotals>,
+  pub pools: BTreeMap<String, ShareTotals>,
+  /// `error` replies not about a share, by pool
+  pub errors: BTreeMap<String, u64>,
+}
+
+#[derive(Clone, Debug)]
+struct PendingShare {
+  nonce: u32,
+  device: u32,
+  pool: String,
+  at: Instant,
+}
+
+/// Shares from their discovery by a GPU to the reply of the pool.
+#[derive(Debug, Default)]
+pub struct ShareLog {
+  report: ShareReport,
+  // The pool new shares are submitted to
+  pool: String,
+  pending: VecDeque<PendingShare>,
+  seen: HashSet<Blake2bHash>,
+  seen_order: VecDeque<Blake2bHash>,
+}
+
+impl ShareLog {
+  /// Attributes shares submitted from now on to `pool`.
+  #[inline]
+  pub fn set_pool(&mut self, pool: String) {
+    self.pool = pool;
+  }
+
+  /// Records a share of `device` and returns whether it should be submitted,
+  /// ie. it was not found before.
+  pub fn found(&mut self, device: u32, block: &Block) -> bool {
+    let hash: Blake2bHash = block.header.hash();
+    let pool: String = self.pool.clone();
+
+    if self.seen.contains(&hash) {
+      self.count(device, &pool, |totals| {
+        totals.found += 1;
+        totals.duplicate += 1;
+      });
+
+      return false;
+    }
+
+    self.seen.insert(hash.clone());
+    self.seen_order.push_back(hash);
+
+    if self.seen_order.len() > SEEN_SHARES {
+      if let Some(hash) = self.seen_order.pop_front() {
+        self.seen.remove(&hash);
+      }
+    }
+
+    self.count(device, &pool, |totals| totals.found += 1);
+
+    true
+  }
+
+  /// Records a share of `device` for outdated work.
+  pub fn stale(&mut self, device: u32) {
+    let pool: String = self.pool.clone();
+
+    self.count(device, &pool, |totals| {
+      totals.found += 1;
+      totals.stale += 1;
+    });
+  }
+
+  /// Counts a share of `device` recorded by `found` as stale, eg. because it
+  /// could not be sent.
+  pub fn unsent(&mut self, device: u32) {
+    let pool: String = self.pool.clone();
+
+    self.count(device, &pool, |totals| totals.stale += 1);
+  }
+
+  /// Records a share with `nonce` of `device` sent to the pool.
+  pub fn submit(&mut self, device: u32, nonce: u32) {
+    let pool: String = self.pool.clone();
+
+    self.count(device, &pool, |totals| totals.submitted += 1);
+
+    self.pending.push_back(PendingShare {
+      nonce,
+      device,
+      pool,
+      at: Instant::now(),
+    });
+
+    self.settle();
+  }
+
+  /// Records an `error` reply and returns the nonce of the share it rejects,
+  /// or `None` if it is not about a share or no share is waiting for a reply.
+  ///
+  /// The pool answers right after receiving a share and at most once per
+  /// share, so a rejection is attributed to the latest share without one.
+  /// Other errors, eg. of a payout request, are counted for the pool.
+  pub fn reject(&mut self, reason: &str) -> Option<u32> {
+    self.settle();
+
+    if !reason.to_lowercase().starts_with(SHARE_REJECTION) {
+      *self.report.errors.entry(self.pool.clone()).or_insert(0) += 1;
+
+      return None;
+    }
+
+    let share: PendingShare = self.pending.pop_back()?;
+
+    self.count(share.device, &share.pool, |totals| {
+      *totals.rejected.entry(reason.into()).or_insert(0) += 1;
+    });
+
+    Some(share.nonce)
+  }
+
+  #[inline]
+  pub fn submitted(&self) -> u64 {
+    self.report.total.submitted
+  }
+
+  #[inline]
+  pub fn rejected(&self) -> u64 {
+    self.report.total.rejected_total()
+  }
+
+  /// Returns the counters, shares without a reply for a while count as
+  /// accepted.
+  pub fn report(&mut self) -> ShareReport {
+    self.settle();
+    self.report.clone()
+  }
+
+  // Accepts the shares the pool did not reply to in time.
+  fn settle(&mut self) {
+    while let Some(share) = self.pending.front().cloned() {
+      if self.pending.len() <= PENDING_SHARES && share.at.elapsed() < ACCEPT_DELAY {
+        break;
+      }
+
+      self.pending.pop_front();
+      self.count(share.device, &share.pool, |totals| totals.accepted += 1);
+    }
+  }
+
+  fn count<F: Fn(&mut ShareTotals)>(&mut self, device: u32, pool: &str, update: F) {
+    update(&mut self.report.total);
+    update(self.report.devices.entry(device).or_default());
+    update(self.report.pools.entry(pool.into()).or_default());
+  }
+}
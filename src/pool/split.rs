@@ -0,0 +1,240 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::pool::PoolEndpoint;
+
+// Mining time is split over this rolling window
+const WINDOW: Duration = Duration::from_secs(60 * 60);
+// Minimum time mined for a split before rotating, registering is not free
+const MIN_SLOT: Duration = Duration::from_secs(2 * 60);
+
+/// A share of the mining time, paid to `address` on `pool`.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Split {
+  /// Wallet address to register with
+  pub address: String,
+  /// Pool server, the active pool of `pools` (or `host`/`port`) if unset
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub pool: Option<PoolEndpoint>,
+  /// Share of the mining time, relative to the other splits (eg. 80 and 20)
+  pub weight: u32,
+}
+
+/// Rotates the registration between splits so that each one gets its share
+/// of the mining time over a rolling window.
+#[derive(Debug)]
+pub struct SplitSchedule {
+  splits: Vec<Split>,
+  active: usize,
+  since: Instant,
+  // Mining time of each split, as (split, recorded at, duration)
+  history: VecDeque<(usize, Instant, Duration)>,
+}
+
+impl SplitSchedule {
+  /// Creates the schedule of `splits`, which must not be empty.
+  pub fn new(splits: Vec<Split>) -> Self {
+    Self {
+      splits,
+      active: 0,
+      since: Instant::now(),
+      history: VecDeque::new(),
+    }
+  }
+
+  #[inline]
+  pub fn active(&self) -> &Split {
+    &self.splits[self.active]
+  }
+
+  #[inline]
+  pub fn splits(&self) -> &[Split] {
+    &self.splits
+  }
+
+  /// Records `elapsed` mining time of the active split.
+  pub fn record(&mut self, elapsed: Duration) {
+    let now: Instant = Instant::now();
+
+    self.history.push_back((self.active, now, elapsed));
+
+    while let Some(&(_, recorded, _)) = self.history.front() {
+      if now.duration_since(recorded) > WINDOW {
+        self.history.pop_front();
+      } else {
+        break;
+      }
+    }
+  }
+
+  /// Returns the split furthest behind its share, once the active one mined
+  /// for the minimum slot.
+  pub fn next(&self) -> Option<usize> {
+    if self.since.elapsed() < MIN_SLOT {
+      return None;
+    }
+
+    let mined: Vec<f64> = self.mined();
+    let total: f64 = mined.iter().sum();
+    let weights: f64 = self.splits.iter().map(|split| f64::from(split.weight)).sum();
+
+    if total <= 0.0 || weights <= 0.0 {
+      return None;
+    }
+
+    let deficit = |index: usize| f64::from(self.splits[index].weight) / weights * total - mined[index];
+
+    let next: usize = (0..self.splits.len())
+      .max_by(|&a, &b| deficit(a).partial_cmp(&deficit(b)).unwrap())
+      .unwrap_or(self.active);
+
+    if next != self.active && deficit(next) > 0.0 {
+      Some(next)
+    } else {
+      None
+    }
+  }
+
+  /// Makes the split at `index` the active one.
+  pub fn switch(&mut self, index: usize) -> &Split {
+    self.active = index;
+    self.since = Instant::now();
+    self.active()
+  }
+
+  /// Returns the realized share of the mining time (0 to 1) of each split
+  /// within the window.
+  pub fn realized(&self) -> Vec<f64> {
+    let mined: Vec<f64> = self.mined();
+    let total: f64 = mined.iter().sum();
+
+    mined
+      .iter()
+      .map(|mined| if total > 0.0 { mined / total } else { 0.0 })
+      .collect()
+  }
+
+  // Seconds mined for each split within the window
+  fn mined(&self) -> Vec<f64> {
+    let mut mined: Vec<f64> = vec![0.0; self.splits.len()];
+
+    for &(index, _, duration) in self.history.iter() {
+      mined[index] += duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1_000_000_000.0;
+    }
+
+    mined
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const MINUTE: Duration = Duration::from_secs(60);
+
+  fn schedule(weights: &[u32]) -> SplitSchedule {
+    let splits: Vec<Split> = weights
+      .iter()
+      .enumerate()
+      .map(|(index, &weight)| Split {
+        address: format!("split {}", index),
+        pool: None,
+        weight,
+      })
+      .collect();
+
+    SplitSchedule::new(splits)
+  }
+
+  // Makes the active split look as if it mined for the minimum slot
+  fn slot_passed(schedule: &mut SplitSchedule) {
+    schedule.since = Instant::now() - MIN_SLOT;
+  }
+
+  #[test]
+  fn waits_for_the_minimum_slot() {
+    let mut schedule: SplitSchedule = schedule(&[80, 20]);
+
+    schedule.record(MINUTE * 30);
+
+    assert_eq!(schedule.next(), None);
+
+    slot_passed(&mut schedule);
+
+    assert_eq!(schedule.next(), Some(1));
+  }
+
+  #[test]
+  fn waits_for_mining_time() {
+    let mut schedule: SplitSchedule = schedule(&[80, 20]);
+
+    slot_passed(&mut schedule);
+
+    assert_eq!(schedule.next(), None);
+  }
+
+  #[test]
+  fn picks_the_split_furthest_behind() {
+    let mut schedule: SplitSchedule = schedule(&[50, 25, 25]);
+
+    schedule.record(MINUTE * 20);
+    schedule.switch(1);
+    schedule.record(MINUTE * 10);
+    slot_passed(&mut schedule);
+
+    // Deficits of -300, -150 and 450 seconds
+    assert_eq!(schedule.next(), Some(2));
+  }
+
+  #[test]
+  fn stays_while_on_target() {
+    let mut schedule: SplitSchedule = schedule(&[1, 1]);
+
+    schedule.record(MINUTE * 10);
+    schedule.switch(1);
+    schedule.record(MINUTE * 10);
+    slot_passed(&mut schedule);
+
+    assert_eq!(schedule.next(), None);
+  }
+
+  #[test]
+  fn stays_while_behind() {
+    let mut schedule: SplitSchedule = schedule(&[80, 20]);
+
+    schedule.switch(1);
+    schedule.record(MINUTE * 5);
+    schedule.switch(0);
+    schedule.record(MINUTE * 5);
+    slot_passed(&mut schedule);
+
+    // The active split is the one furthest behind
+    assert_eq!(schedule.next(), None);
+  }
+
+  #[test]
+  fn switching_restarts_the_slot() {
+    let mut schedule: SplitSchedule = schedule(&[80, 20]);
+
+    schedule.record(MINUTE * 30);
+    slot_passed(&mut schedule);
+
+    assert_eq!(schedule.switch(1).address, "split 1");
+    assert_eq!(schedule.next(), None);
+  }
+
+  #[test]
+  fn realized_shares() {
+    let mut schedule: SplitSchedule = schedule(&[1, 1, 1]);
+
+    assert_eq!(schedule.realized(), vec![0.0, 0.0, 0.0]);
+
+    schedule.record(MINUTE * 30);
+    schedule.switch(1);
+    schedule.record(MINUTE * 10);
+
+    assert_eq!(schedule.realized(), vec![0.75, 0.25, 0.0]);
+  }
+}
@@ -0,0 +1,286 @@
+use beserial::Serialize;
+use nimiq_block::Block;
+use nimiq_block::BlockBody;
+use nimiq_block::BlockHeader;
+use nimiq_block::BlockInterlink;
+use nimiq_block::Target;
+use nimiq_blockchain::Blockchain;
+use nimiq_hash::Blake2bHash;
+use nimiq_hash::Hash;
+use nimiq_keys::Address;
+use nimiq_mempool::Mempool;
+use nimiq_mempool::MempoolEvent;
+use nimiq_primitives::policy::BLOCK_SIZE_MAX;
+use nimiq_transaction::Transaction;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::error::Error;
+
+type ArcChain = Arc<Blockchain<'static>>;
+type ArcMempool = Arc<Mempool<'static>>;
+
+const TAG: &'static str = "BlockTemplate";
+
+// Mempool transactions considered for a block
+const MAX_CANDIDATES: usize = 5_000;
+// New fees, relative to those of the last template, worth rebuilding for
+const REBUILD_FEES: f64 = 0.1;
+
+#[derive(Debug, Default)]
+struct MempoolChanges {
+  // Fees collected by the last template
+  fees: u64,
+  // Fees of the transactions added to the mempool since
+  added: u64,
+  // Whether transactions were evicted since, they might be in the template
+  evicted: bool,
+}
+
+/// Builds full blocks on top of the local head.
+///
+/// Transactions are picked from the mempool by fee per byte, as many as fit
+/// into a block. Listeners of the mempool keep track of the changes since the
+/// last block, see `is_outdated`.
+#[derive(Clone)]
+pub struct BlockTemplate {
+  blockchain: ArcChain,
+  mempool: ArcMempool,
+  genesis: Blake2bHash,
+  changes: Arc<Mutex<MempoolChanges>>,
+}
+
+impl BlockTemplate {
+  pub fn new(blockchain: ArcChain, mempool: ArcMempool, genesis: Blake2bHash) -> Self {
+    let changes: Arc<Mutex<MempoolChanges>> = Arc::default();
+
+    {
+      let changes: Arc<Mutex<MempoolChanges>> = Arc::clone(&changes);
+
+      mempool.notifier.write().register(move |event: &MempoolEvent| {
+        let mut changes = changes.lock().unwrap();
+
+        match event {
+          MempoolEvent::TransactionAdded(_, transaction) | MempoolEvent::TransactionRestored(transaction) => {
+            changes.added += u64::from(transaction.fee);
+          }
+          MempoolEvent::TransactionEvicted(_) => {
+            changes.evicted = true;
+          }
+          // Mined transactions come with a new head
+          MempoolEvent::TransactionMined(_) => {}
+        }
+      });
+    }
+
+    Self {
+      blockchain,
+      mempool,
+      genesis,
+      changes,
+    }
+  }
+
+  /// Returns the next block, with transactions from the mempool and the
+  /// reward paid to `miner`.
+  ///
+  /// Transactions that cannot be applied to the accounts are left out of the
+  /// block, the others are kept.
+  pub fn next(&self, timestamp: u32, miner: Address, extra: Vec<u8>) -> Result<Block, Error> {
+    let head: Block = self.blockchain.head().clone();
+    let head_hash: Blake2bHash = head.header.hash();
+
+    let next_target: Target = self.blockchain.get_next_target(Some(&head_hash));
+    let interlink: BlockInterlink = head.get_next_interlink(&next_target);
+
+    let max_size: usize =
+      BLOCK_SIZE_MAX - BlockHeader::SIZE - interlink.serialized_size() - BlockBody::get_metadata_size(extra.len());
+
+    let transactions: Vec<Transaction> = self.select(max_size);
+
+    let header = BlockHeader {
+      timestamp,
+      nonce: 0,
+      version: Block::VERSION,
+      prev_hash: head_hash,
+      interlink_hash: interlink.hash(self.genesis.clone()),
+      body_hash: Blake2bHash::default(),
+      accounts_hash: Blake2bHash::default(),
+      n_bits: next_target.into(),
+      height: head.header.height + 1,
+    };
+
+    let body: BlockBody = self.body(&header, max_size, miner, extra, transactions)?;
+
+    let fees: u64 = body
+      .transactions
+      .iter()
+      .map(|transaction| u64::from(transaction.fee))
+      .sum();
+
+    let accounts_hash: Blake2bHash = self
+      .blockchain
+      .state()
+      .accounts()
+      .hash_with_block_body(&body, header.height)
+      .map_err(|error| Error::from(format!("Failed to apply block body: {:?}", error)))?;
+
+    *self.changes.lock().unwrap() = MempoolChanges {
+      fees,
+      ..MempoolChanges::default()
+    };
+
+    Ok(Block {
+      header: BlockHeader {
+        body_hash: body.hash(),
+        accounts_hash,
+        ..header
+      },
+      interlink,
+      body: Some(body),
+    })
+  }
+
+  /// Returns whether the mempool changed enough since the last block to build
+  /// a new one: transactions were evicted, or new ones add at least
+  /// `REBUILD_FEES` to its fees.
+  pub fn is_outdated(&self) -> bool {
+    let changes = self.changes.lock().unwrap();
+
+    changes.evicted || (changes.added > 0 && changes.added as f64 >= changes.fees as f64 * REBUILD_FEES)
+  }
+
+  // The transactions with the highest fee per byte that fit into `max_size`,
+  // best first. Those that would spend more than their sender has are skipped.
+  fn select(&self, max_size: usize) -> Vec<Transaction> {
+    let mut candidates: Vec<Arc<Transaction>> = self.mempool.get_transactions(MAX_CANDIDATES, 0.0);
+
+    candidates.sort_by(|a, b| {
+      b.fee_per_byte()
+        .partial_cmp(&a.fee_per_byte())
+        .unwrap_or(Ordering::Equal)
+    });
+
+    let state = self.blockchain.state();
+    let accounts = state.accounts();
+
+    let mut size: usize = 0;
+    let mut spent: HashMap<Address, u64> = HashMap::new();
+    let mut transactions: Vec<Transaction> = Vec::new();
+
+    for transaction in candidates {
+      let length: usize = transaction.serialized_size();
+
+      // Smaller ones might still fit
+      if size + length > max_size {
+        continue;
+      }
+
+      let total: u64 = u64::from(transaction.value) + u64::from(transaction.fee);
+      let sender: &mut u64 = spent.entry(transaction.sender.clone()).or_insert(0);
+
+      // Cheaper ones of the same sender might still be covered
+      if *sender + total > u64::from(accounts.get(&transaction.sender, None).balance()) {
+        continue;
+      }
+
+      *sender += total;
+      size += length;
+      transactions.push((*transaction).clone());
+    }
+
+    transactions
+  }
+
+  // The body with `transactions` and their receipts, dropping those that
+  // cannot be applied, then the last ones until the receipts fit as well.
+  fn body(
+    &self,
+    header: &BlockHeader,
+    max_size: usize,
+    miner: Address,
+    extra_data: Vec<u8>,
+    mut transactions: Vec<Transaction>,
+  ) -> Result<BlockBody, Error> {
+    loop {
+      let result = self
+        .blockchain
+        .state()
+        .accounts()
+        .collect_receipts(&transactions, header.height);
+
+      let mut receipts = match result {
+        Ok(receipts) => receipts,
+        Err(error) => {
+          if transactions.is_empty() {
+            Err(format!("Failed to collect receipts: {:?}", error))?
+          }
+
+          let transaction: Transaction = transactions.remove(self.offending(&transactions, header.height));
+          eprintln!(
+            "[{}] Dropping transaction {}: {:?}",
+            TAG,
+            transaction.hash::<Blake2bHash>(),
+            error
+          );
+          continue;
+        }
+      };
+
+      let size: usize = transactions.iter().map(Serialize::serialized_size).sum::<usize>()
+        + receipts.iter().map(Serialize::serialized_size).sum::<usize>();
+
+      if size > max_size && transactions.pop().is_some() {
+        continue;
+      }
+
+      transactions.sort_unstable_by(|a, b| a.cmp_block_order(b));
+      receipts.sort_unstable();
+
+      return Ok(BlockBody {
+        miner,
+        extra_data,
+        transactions,
+        receipts,
+      });
+    }
+  }
+
+  // The index of a transaction that cannot be applied after those before it,
+  // given that `transactions` as a whole cannot be applied.
+  fn offending(&self, transactions: &[Transaction], height: u32) -> usize {
+    let mut applied: usize = 0;
+    let mut failed: usize = transactions.len();
+
+    while failed - applied > 1 {
+      let middle: usize = (applied + failed) / 2;
+      let result = self
+        .blockchain
+        .state()
+        .accounts()
+        .collect_receipts(&transactions[..middle].to_vec(), height);
+
+      if result.is_ok() {
+        applied = middle;
+      } else {
+        failed = middle;
+      }
+    }
+
+    applied
+  }
+}
+
+impl Debug for BlockTemplate {
+  fn fmt(&self, f: &mut Formatter) -> FmtResult {
+    f.debug_struct("BlockTemplate")
+      .field("height", &self.blockchain.height())
+      .field("changes", &*self.changes.lock().unwrap())
+      .finish()
+  }
+}
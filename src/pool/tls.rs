@@ -0,0 +1,91 @@
+use hex::encode;
+use native_tls::Certificate;
+use native_tls::TlsConnector;
+use native_tls::TlsConnectorBuilder;
+use sha2::Digest;
+use sha2::Sha256;
+use std::fs::read_to_string;
+use std::path::PathBuf;
+
+use crate::error::Error;
+use crate::pool::PoolConfig;
+
+const PEM_END: &'static str = "-----END CERTIFICATE-----";
+
+/// TLS settings of `wss` pool connections.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TlsConfig {
+  /// PEM bundle of CA certificates trusted in addition to the system ones
+  pub ca: Option<String>,
+  /// SHA-256 fingerprints of the accepted server certificates
+  pub pins: Vec<String>,
+  /// Accepts any server certificate
+  pub insecure: bool,
+}
+
+impl TlsConfig {
+  /// Builds the connector of the pool connections.
+  ///
+  /// Pinned certificates are trusted on their own, so self-signed pool
+  /// certificates work without a CA bundle.
+  pub fn connector(&self) -> Result<TlsConnector, Error> {
+    let mut builder: TlsConnectorBuilder = TlsConnector::builder();
+
+    if let Some(ref path) = self.ca {
+      let bundle: String = read_to_string(path).map_err(|error| Error::ConfigFile(PathBuf::from(path), error))?;
+
+      for pem in bundle.split(PEM_END).filter(|pem| !pem.trim().is_empty()) {
+        builder.add_root_certificate(Certificate::from_pem(format!("{}{}", pem, PEM_END).as_bytes())?);
+      }
+    }
+
+    if self.insecure || !self.pins.is_empty() {
+      builder.danger_accept_invalid_certs(true);
+      builder.danger_accept_invalid_hostnames(true);
+    }
+
+    Ok(builder.build()?)
+  }
+
+  /// Checks the server `certificate` against the pins, if any.
+  pub fn verify(&self, certificate: Option<Certificate>) -> Result<(), Error> {
+    if self.pins.is_empty() {
+      return Ok(());
+    }
+
+    let certificate: Certificate = certificate.ok_or("Pool sent no TLS certificate")?;
+    let fingerprint: String = encode(Sha256::digest(&certificate.to_der()?));
+
+    if self
+      .pins
+      .iter()
+      .any(|pin| normalize_pin(pin).as_ref() == Some(&fingerprint))
+    {
+      Ok(())
+    } else {
+      Err(format!("TLS certificate {} is not pinned", fingerprint))?
+    }
+  }
+}
+
+impl<'a> From<&'a PoolConfig> for TlsConfig {
+  fn from(other: &'a PoolConfig) -> Self {
+    Self {
+      ca: other.tls_ca.clone(),
+      pins: other.tls_pins.clone(),
+      insecure: other.tls_insecure,
+    }
+  }
+}
+
+/// Returns the lowercase hex of a SHA-256 fingerprint, eg. `AB:CD:...` as
+/// printed by `openssl x509 -fingerprint -sha256`, or `None` if it is invalid.
+pub fn normalize_pin(pin: &str) -> Option<String> {
+  let pin: String = pin.trim().replace(':', "").to_lowercase();
+
+  if pin.len() == 64 && pin.chars().all(|char| char.is_ascii_hexdigit()) {
+    Some(pin)
+  } else {
+    None
+  }
+}
@@ -0,0 +1,71 @@
+use futures::future::result;
+use futures::future::Either;
+use futures::Future;
+use native_tls::TlsConnector as NativeConnector;
+use std::net::SocketAddr;
+use std::net::ToSocketAddrs;
+use tokio::net::TcpStream;
+use tokio_tls::TlsConnector;
+use tokio_tls::TlsStream;
+use tokio_tungstenite::client_async;
+use tokio_tungstenite::stream::Stream;
+use tokio_tungstenite::WebSocketStream;
+use url::Url;
+
+use crate::error::Error;
+use crate::pool::open_tunnel;
+use crate::pool::PoolProxy;
+use crate::pool::TlsConfig;
+
+/// A plaintext (`ws`) or TLS (`wss`) pool connection.
+pub type PoolStream = Stream<TcpStream, TlsStream<TcpStream>>;
+
+/// An open websocket to the pool.
+pub type PoolSocket = WebSocketStream<PoolStream>;
+
+/// Opens the websocket at `url`, through `proxy` if set, and over TLS with
+/// `tls` for `wss` urls.
+pub fn open_socket(
+  url: Url,
+  tls: TlsConfig,
+  proxy: Option<PoolProxy>,
+) -> impl Future<Item = PoolSocket, Error = Error> {
+  let host: String = url.host_str().unwrap_or_default().to_owned();
+  let port: u16 = url.port_or_known_default().unwrap_or(443);
+  let secure: bool = url.scheme() == "wss";
+
+  let stream = match proxy {
+    Some(proxy) => Either::A(open_tunnel(proxy, host.clone(), port)),
+    None => Either::B(result(resolve(&host, port)).and_then(|address| TcpStream::connect(&address).map_err(Error::IO))),
+  };
+
+  stream
+    .and_then(move |stream| {
+      if secure {
+        Either::A(handshake(stream, host, tls))
+      } else {
+        Either::B(result::<PoolStream, Error>(Ok(Stream::Plain(stream))))
+      }
+    })
+    .and_then(move |stream| client_async(url, stream).map_err(Error::Connect))
+    .map(|(socket, _)| socket)
+}
+
+/// Returns the first address of `host`.
+pub fn resolve(host: &str, port: u16) -> Result<SocketAddr, Error> {
+  let address: Option<SocketAddr> = (host, port).to_socket_addrs()?.next();
+
+  address.ok_or_else(|| format!("No address found for `{}`", host).into())
+}
+
+fn handshake(stream: TcpStream, host: String, tls: TlsConfig) -> impl Future<Item = PoolStream, Error = Error> {
+  result(tls.connector())
+    .and_then(move |connector: NativeConnector| {
+      TlsConnector::from(connector).connect(&host, stream).map_err(Error::TLS)
+    })
+    .and_then(move |stream| -> Result<PoolStream, Error> {
+      tls.verify(stream.get_ref().peer_certificate()?)?;
+
+      Ok(Stream::Tls(stream))
+    })
+}
@@ -0,0 +1,252 @@
+use nimiq_block::Block;
+use nimiq_blockchain::Blockchain;
+use nimiq_hash::Blake2bHash;
+use nimiq_hash::Hash;
+use nimiq_keys::Address;
+use nimiq_mempool::Mempool;
+use nimiq_network::Network;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::sync::mpsc::channel;
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread::spawn;
+
+use crate::error::Error;
+use crate::miner::Found;
+use crate::miner::Miner;
+use crate::miner::MinerConfig;
+use crate::miner::MINER;
+use crate::pool::publish_block;
+use crate::pool::BlockTemplate;
+use crate::pool::PoolConfig;
+
+type ArcChain = Arc<Blockchain<'static>>;
+type ArcNetwork = Arc<Network>;
+type ArcMempool = Arc<Mempool<'static>>;
+
+const TAG: &'static str = "SoloMiner";
+
+// Longest extraData accepted in a block body
+const MAX_EXTRA_DATA: usize = 255;
+
+/// Mines blocks of the local node to the network target, without a pool.
+///
+/// Found blocks are pushed into the local blockchain, the consensus relays
+/// them to the peers of the network.
+pub struct SoloMiner {
+  blockchain: ArcChain,
+  network: ArcNetwork,
+  config: PoolConfig,
+  template: BlockTemplate,
+  address: Address,
+  extra_data: Vec<u8>,
+  work: Mutex<Sender<Block>>,
+  enabled: bool,
+  connected: bool,
+}
+
+impl Debug for SoloMiner {
+  fn fmt(&self, f: &mut Formatter) -> FmtResult {
+    f.debug_struct("SoloMiner")
+      .field("config", &self.config)
+      .field("template", &self.template)
+      .field("enabled", &self.enabled)
+      .field("connected", &self.connected)
+      .finish()
+  }
+}
+
+impl SoloMiner {
+  pub fn new(
+    blockchain: ArcChain,
+    network: ArcNetwork,
+    mempool: ArcMempool,
+    config: PoolConfig,
+  ) -> Result<Self, Error> {
+    {
+      let mut miner = MINER.write().unwrap();
+
+      miner.configure(MinerConfig::from(&config));
+      miner.initialize()?;
+    }
+
+    let address: Address = Self::address(&config)?;
+    let work: Sender<Block> = Self::spawn_worker(Arc::clone(&blockchain));
+    let template: BlockTemplate = BlockTemplate::new(Arc::clone(&blockchain), mempool, config.network.genesis_hash());
+
+    Ok(Self {
+      extra_data: Self::extra_data(&config),
+      blockchain,
+      network,
+      config,
+      template,
+      address,
+      work: Mutex::new(work),
+      enabled: false,
+      connected: false,
+    })
+  }
+
+  // Mines blocks on a separate thread. Every share is a block, found blocks
+  // are pushed right away.
+  fn spawn_worker(blockchain: ArcChain) -> Sender<Block> {
+    let (sender, receiver) = channel::<Block>();
+
+    spawn(move || {
+      while let Ok(mut block) = receiver.recv() {
+        // Skip outdated work
+        while let Ok(next) = receiver.try_recv() {
+          block = next;
+        }
+
+        let push = |_: u32, found: Found| {
+          let block: Block = match found {
+            Found::Share(block) => block,
+            Found::Stale(nonce) => return println!("[{}] Stale Block: {}", TAG, nonce),
+          };
+
+          let hash: Blake2bHash = block.header.hash();
+
+          if !block.header.verify_proof_of_work() {
+            eprintln!("[{}] Block {} does not meet the network target", TAG, hash);
+            return;
+          }
+
+          println!("[{}] Found Block #{}: {}", TAG, block.header.height, hash);
+
+          publish_block(Arc::clone(&blockchain), block);
+        };
+
+        let mut miner = MINER.write().unwrap();
+
+        // Shares are blocks
+        miner.scompact(u32::from(block.header.n_bits));
+
+        if let Err(error) = miner.mine(block, Arc::clone(&blockchain), push) {
+          eprintln!("[{}] Mining Error: {:?}", TAG, error);
+        }
+      }
+    });
+
+    sender
+  }
+
+  /// Applies a new config without restarting the node.
+  ///
+  /// A new address or name changes the next block, new `devices`/`memory`
+  /// rebuild the affected GPU workers.
+  pub fn reload(&mut self, mut config: PoolConfig) -> Result<(), Error> {
+    if config == self.config {
+      println!("[{}] Config unchanged", TAG);
+      return Ok(());
+    }
+
+    // The blockchain belongs to the network it was started with
+    if config.network != self.config.network {
+      eprintln!("[{}] Network is only applied on restart", TAG);
+
+      config.network = self.config.network;
+    }
+
+    if config.mode != self.config.mode {
+      eprintln!("[{}] Mode is only applied on restart", TAG);
+
+      config.mode = self.config.mode;
+    }
+
+    let rebuild: bool = config.devices != self.config.devices
+      || config.memory != self.config.memory
+      || config.profiles != self.config.profiles
+      || config.overrides != self.config.overrides;
+
+    if rebuild {
+      println!("[{}] Rebuilding GPU workers", TAG);
+
+      Miner::interrupt();
+
+      MINER.write().unwrap().reconfigure(MinerConfig::from(&config))?;
+    }
+
+    self.address = Self::address(&config)?;
+    self.extra_data = Self::extra_data(&config);
+    self.config = config;
+
+    if self.enabled {
+      self.start_mining();
+    }
+
+    Ok(())
+  }
+
+  /// Starts mining once the node reached consensus.
+  pub fn connect(&mut self) {
+    self.connected = true;
+    self.start_mining();
+  }
+
+  /// Stops mining while the node has no consensus, blocks would be orphaned.
+  pub fn disconnect(&mut self) {
+    self.connected = false;
+    self.stop_mining();
+  }
+
+  /// Rebuilds the block on top of the new head.
+  pub fn head_changed(&mut self) {
+    if self.connected {
+      self.start_mining();
+    }
+  }
+
+  /// Builds a new block if the mempool changed significantly, so the fees of
+  /// new transactions are collected.
+  pub fn mempool_changed(&mut self) {
+    if self.enabled && self.template.is_outdated() {
+      println!("[{}] Mempool changed, rebuilding block", TAG);
+
+      self.start_mining();
+    }
+  }
+
+  fn start_mining(&mut self) {
+    let now: u64 = self.network.network_time.now();
+    let now: u32 = (now / 1000) as u32;
+    let time: u32 = now.max(self.blockchain.head().header.timestamp + 1);
+
+    let block: Block = match self.template.next(time, self.address.clone(), self.extra_data.clone()) {
+      Ok(block) => block,
+      Err(error) => {
+        eprintln!("[{}] Template Error: {:?}", TAG, error);
+        return;
+      }
+    };
+
+    self.enabled = true;
+
+    println!("[{}] Starting work on block #{}", TAG, block.header.height);
+
+    Miner::interrupt();
+
+    if self.work.lock().unwrap().send(block).is_err() {
+      eprintln!("[{}] Mining thread stopped", TAG);
+    }
+  }
+
+  fn stop_mining(&mut self) {
+    self.enabled = false;
+
+    Miner::interrupt();
+  }
+
+  fn address(config: &PoolConfig) -> Result<Address, Error> {
+    Address::from_user_friendly_address(&config.address)
+      .map_err(|error| Error::Config("address".into(), format!("{:?}", error)))
+  }
+
+  // The device name, as far as it fits
+  fn extra_data(config: &PoolConfig) -> Vec<u8> {
+    config.name.bytes().take(MAX_EXTRA_DATA).collect()
+  }
+}
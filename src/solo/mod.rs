@@ -0,0 +1,3 @@
+mod miner;
+
+pub use self::miner::*;
@@ -25,3 +25,109 @@ pub fn bytes_into_string(mut bytes: Vec<u8>) -> String {
     .map(|string| String::from(string.trim()))
     .unwrap()
 }
+
+/// Removes `//` and `/* */` comments from a JSON document, leaving
+/// string literals untouched.
+pub fn strip_comments(input: &str) -> String {
+  let mut output: String = String::with_capacity(input.len());
+  let mut chars = input.chars().peekable();
+  let mut string: bool = false;
+
+  while let Some(current) = chars.next() {
+    if string {
+      output.push(current);
+
+      if current == '\\' {
+        if let Some(next) = chars.next() {
+          output.push(next);
+        }
+      } else if current == '"' {
+        string = false;
+      }
+
+      continue;
+    }
+
+    match (current, chars.peek()) {
+      ('"', _) => {
+        string = true;
+        output.push(current);
+      }
+      ('/', Some('/')) => {
+        while let Some(&next) = chars.peek() {
+          if next == '\n' {
+            break;
+          }
+
+          chars.next();
+        }
+      }
+      ('/', Some('*')) => {
+        chars.next();
+
+        let mut last: char = '\0';
+
+        while let Some(next) = chars.next() {
+          if last == '*' && next == '/' {
+            break;
+          }
+
+          // Keep line breaks so parser errors report the right line
+          if next == '\n' {
+            output.push(next);
+          }
+
+          last = next;
+        }
+      }
+      _ => output.push(current),
+    }
+  }
+
+  output
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn strips_line_comments() {
+    let input: &str = "{\n  // pool\n  \"port\": 8443 // wss\n}";
+
+    assert_eq!(strip_comments(input), "{\n  \n  \"port\": 8443 \n}");
+  }
+
+  #[test]
+  fn strips_block_comments_keeping_lines() {
+    let input: &str = "{ /* one\ntwo */ \"port\": 8443 }";
+
+    assert_eq!(strip_comments(input), "{ \n \"port\": 8443 }");
+  }
+
+  #[test]
+  fn keeps_comments_in_strings() {
+    let input: &str = r#"{ "host": "wss://pool.example.com//x", "name": "/* rig */" }"#;
+
+    assert_eq!(strip_comments(input), input);
+  }
+
+  #[test]
+  fn keeps_escaped_quotes_in_strings() {
+    let input: &str = r#"{ "name": "say \"// hi\"" } // comment"#;
+
+    assert_eq!(strip_comments(input), r#"{ "name": "say \"// hi\"" } "#);
+  }
+
+  #[test]
+  fn keeps_single_slashes() {
+    let input: &str = "{ \"ratio\": 1 / 2 }";
+
+    assert_eq!(strip_comments(input), input);
+  }
+
+  #[test]
+  fn strips_unterminated_block_comments() {
+    assert_eq!(strip_comments("{} /* to the end"), "{} ");
+  }
+}